@@ -0,0 +1,143 @@
+use crate::*;
+use std::fs::File;
+use std::io::{self, Write, BufReader, BufWriter};
+use std::path::Path;
+use crate::lazy_archive::read_full;
+use chacha20poly1305::{XChaCha20Poly1305, KeyInit};
+use chacha20poly1305::aead::{OsRng, rand_core::RngCore};
+use chacha20poly1305::aead::stream::{EncryptorBE32, DecryptorBE32};
+
+/* Encrypted archive format
+ * - `ENCRYPTED_MAGIC`
+ * - argon2 salt (16 bytes)
+ * - stream nonce (19 bytes)
+ * - XChaCha20-Poly1305 STREAM chunks of `CHUNK_SIZE` plaintext bytes (each followed by a 16 byte tag);
+ *   the last chunk is always shorter than `CHUNK_SIZE` (possibly empty)
+ */
+
+use crate::lazy_archive::ENCRYPTED_MAGIC;
+pub(crate) const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 19;
+const CHUNK_SIZE: usize = 65536;
+const TAG_SIZE: usize = 16;
+
+/// A key derived from a passphrase along with the salt it was derived with
+pub(crate) struct ArchiveKey {
+    salt: [u8; SALT_SIZE],
+    key: [u8; 32],
+}
+
+impl ArchiveKey {
+    /// Derives a key from a passphrase with a new random salt
+    pub fn new(passphrase: &str) -> Result<Self, LDBError> {
+        Self::derive(passphrase, new_salt())
+    }
+
+    /// Derives a key from a passphrase and salt
+    pub fn derive(passphrase: &str, salt: [u8; SALT_SIZE]) -> Result<Self, LDBError> {
+        Ok(Self { salt, key: derive_key(passphrase, &salt)? })
+    }
+}
+
+/// Generates a new random salt
+pub(crate) fn new_salt() -> [u8; SALT_SIZE] {
+    let mut salt = [0u8; SALT_SIZE];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+/// Derives key material from a passphrase and salt with `argon2`
+pub(crate) fn derive_key<const N: usize>(passphrase: &str, salt: &[u8]) -> Result<[u8; N], LDBError> {
+    let mut key = [0u8; N];
+    unwrap_result!((argon2::Argon2::default().hash_password_into(passphrase.as_bytes(), salt, &mut key))
+        err => LDBError::IOError(io::Error::new(io::ErrorKind::InvalidInput, err.to_string())));
+    Ok(key)
+}
+
+fn encryption_failed() -> LDBError {
+    LDBError::IOError(io::Error::other("Encryption failed"))
+}
+
+/// Encrypts a compiled archive into an encrypted archive
+pub(crate) fn encrypt_file(key: &ArchiveKey, path: impl AsRef<Path>, out_path: impl AsRef<Path>) -> Result<(), LDBError> {
+    let mut input = BufReader::new(unwrap_result!((File::open(path)) err => LDBError::IOError(err)));
+    let mut out = BufWriter::new(unwrap_result!((File::create(out_path)) err => LDBError::IOError(err)));
+
+    // Write header
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+    unwrap_result!((out.write_all(ENCRYPTED_MAGIC)) err => LDBError::IOError(err));
+    unwrap_result!((out.write_all(&key.salt)) err => LDBError::IOError(err));
+    unwrap_result!((out.write_all(&nonce)) err => LDBError::IOError(err));
+
+    // Encrypt chunks
+    let cipher = XChaCha20Poly1305::new(&key.key.into());
+    let mut encryptor = EncryptorBE32::from_aead(cipher, &nonce.into());
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        let read = unwrap_result!((read_full(&mut input, &mut buffer)) err => LDBError::IOError(err));
+        if read < CHUNK_SIZE {
+            let chunk = encryptor.encrypt_last(&buffer[..read]).map_err(|_| encryption_failed())?;
+            unwrap_result!((out.write_all(&chunk)) err => LDBError::IOError(err));
+            break;
+        }
+        let chunk = encryptor.encrypt_next(buffer.as_slice()).map_err(|_| encryption_failed())?;
+        unwrap_result!((out.write_all(&chunk)) err => LDBError::IOError(err));
+    }
+
+    unwrap_result!((out.flush()) err => LDBError::IOError(err));
+    Ok(())
+}
+
+/// Decrypts an encrypted archive with a passphrase into a writer; returns the key it was encrypted with
+///
+/// Returns `LDBError::DecryptionFailed` if the passphrase is wrong or the archive has been tampered with
+pub(crate) fn decrypt_file(passphrase: &str, path: impl AsRef<Path>, out: impl Write) -> Result<ArchiveKey, LDBError> {
+    decrypt(path.as_ref(), out, |salt| ArchiveKey::derive(passphrase, salt))
+}
+
+/// Checks that an encrypted archive decrypts with a key
+///
+/// Returns `LDBError::DecryptionFailed` if the archive was encrypted with another key or has been tampered with
+pub(crate) fn verify_file(key: &ArchiveKey, path: impl AsRef<Path>) -> Result<(), LDBError> {
+    let path = path.as_ref();
+    decrypt(path, io::sink(), |salt| if salt == key.salt {
+        Ok(ArchiveKey { salt, key: key.key })
+    } else {
+        Err(LDBError::DecryptionFailed(path.to_path_buf()))
+    })?;
+    Ok(())
+}
+
+/// Decrypts an encrypted archive into a writer with the key for it's salt
+fn decrypt(path: &Path, out: impl Write, key: impl FnOnce([u8; SALT_SIZE]) -> Result<ArchiveKey, LDBError>) -> Result<ArchiveKey, LDBError> {
+    let failed = || LDBError::DecryptionFailed(path.to_path_buf());
+    let mut input = BufReader::new(unwrap_result!((File::open(path)) err => LDBError::IOError(err)));
+
+    // Read header
+    let mut header = [0u8; 8 + SALT_SIZE + NONCE_SIZE];
+    if unwrap_result!((read_full(&mut input, &mut header)) err => LDBError::IOError(err)) != header.len()
+        || &header[..8] != ENCRYPTED_MAGIC { return Err(failed()) };
+    let salt: [u8; SALT_SIZE] = header[8..8 + SALT_SIZE].try_into().unwrap();
+    let nonce: [u8; NONCE_SIZE] = header[8 + SALT_SIZE..].try_into().unwrap();
+    let key = key(salt)?;
+
+    // Decrypt chunks
+    let mut out = BufWriter::new(out);
+    let cipher = XChaCha20Poly1305::new(&key.key.into());
+    let mut decryptor = DecryptorBE32::from_aead(cipher, &nonce.into());
+    let mut buffer = vec![0u8; CHUNK_SIZE + TAG_SIZE];
+    loop {
+        let read = unwrap_result!((read_full(&mut input, &mut buffer)) err => LDBError::IOError(err));
+        if read < CHUNK_SIZE + TAG_SIZE {
+            let chunk = decryptor.decrypt_last(&buffer[..read]).map_err(|_| failed())?;
+            unwrap_result!((out.write_all(&chunk)) err => LDBError::IOError(err));
+            break;
+        }
+        let chunk = decryptor.decrypt_next(buffer.as_slice()).map_err(|_| failed())?;
+        unwrap_result!((out.write_all(&chunk)) err => LDBError::IOError(err));
+    }
+
+    unwrap_result!((out.flush()) err => LDBError::IOError(err));
+    Ok(key)
+}
@@ -11,7 +11,15 @@ pub enum LDBError {
     InvalidUTF8String(Box<[u8]>),
     InvalidNumberByteLength(u8, String),
     InvalidMetaVersion(PathBuf),
-    IncompatibleVersion(crate::version::Version),
+    /// The version found, the current version and the policy that rejected it
+    IncompatibleVersion(crate::version::Version, crate::version::Version, crate::version::VersionPolicy),
+    DecryptionFailed(PathBuf),
+    /// The database's values are encrypted (the path of it's `.crypt` file), but no passphrase was given
+    PassphraseRequired(PathBuf),
+    InvalidKey(String, String),
+    InvalidPath(String, String),
+    AlreadyExists(PathBuf),
+    Locked(PathBuf),
 }
 
 impl fmt::Display for LDBError {
@@ -26,7 +34,13 @@ impl fmt::Display for LDBError {
             InvalidUTF8String(x) => write!(f, "Bytes represent an invalid utf8 string: {:?}", x),
             InvalidNumberByteLength(x, t) => write!(f, "Invalid byte length '{x}' for number type '{t:?}'"),
             InvalidMetaVersion(p) => write!(f, "Invalid version for `lazy-db` at '{}'", p.to_string_lossy()),
-            IncompatibleVersion(v) => write!(f, "Found version '{v}' incompatible with current version '{}'", crate::VERSION),
+            IncompatibleVersion(v, c, p) => write!(f, "Found version '{v}' incompatible with current version '{c}' (policy: {p})"),
+            DecryptionFailed(p) => write!(f, "Failed to decrypt '{}' (wrong key or tampered data)", p.to_string_lossy()),
+            PassphraseRequired(p) => write!(f, "Values are encrypted (see '{}'), but no passphrase was given", p.to_string_lossy()),
+            InvalidKey(k, r) => write!(f, "Invalid key '{}': {r}", k.escape_debug()),
+            InvalidPath(p, r) => write!(f, "Invalid path '{}': {r}", p.escape_debug()),
+            AlreadyExists(p) => write!(f, "'{}' already exists", p.to_string_lossy()),
+            Locked(p) => write!(f, "Database is locked by another process (lock file '{}')", p.to_string_lossy()),
         }
     }
 }
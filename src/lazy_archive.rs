@@ -1,39 +1,63 @@
 use std::fs::File;
-use std::io::{self, Read, Write};
+use std::io::{self, Read, Write, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
-use tar::Builder;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tar::{Builder, Header};
+use crate::storage::Storage;
 use lz4_flex::frame::{FrameEncoder, FrameDecoder};
 use std::fs;
 
 const BUFFER_SIZE: usize = 8192;
 
-pub fn build_tar(path: impl AsRef<Path>, tar_path: impl AsRef<Path>) -> Result<(), io::Error> {
+/// Checks if a file only matters while the database is open (like it's lock file or it's snapshots), so it's left out of archives
+pub fn is_transient(name: &std::ffi::OsStr) -> bool {
+    name == crate::lazy_database::LOCK_FILE || name == crate::storage::WAL_FILE || name == crate::lazy_database::SNAPSHOTS_DIR
+}
+
+pub fn build_tar(storage: &dyn Storage, path: impl AsRef<Path>, tar_path: impl AsRef<Path>) -> Result<(), io::Error> {
     let tar = File::create(tar_path)?;
     let mut builder = Builder::new(tar);
 
-    recursive_tar_append(&mut builder, path, PathBuf::new())?;
+    recursive_tar_append(storage, &mut builder, path, PathBuf::new())?;
 
     builder.finish()?;
     Ok(())
 }
 
-fn recursive_tar_append(builder: &mut Builder<File>, path: impl AsRef<Path>, tar_path: PathBuf) -> Result<(), io::Error> {
-    for entry in std::fs::read_dir(path)?.filter_map(|x| x.ok()) {
-        let path = entry.path();
-        let file_type = entry.file_type()?;
-        if file_type.is_file() {
-            builder.append_file(
-                tar_path.join(entry.file_name()),
-                &mut File::open(&path)?
-            )?;
-        } else if file_type.is_dir() {
-            recursive_tar_append(builder, path, tar_path.join(entry.file_name()))?;
+fn recursive_tar_append(storage: &dyn Storage, builder: &mut Builder<File>, path: impl AsRef<Path>, tar_path: PathBuf) -> Result<(), io::Error> {
+    let path = path.as_ref();
+    for entry in storage.read_dir(path)? {
+        if is_transient(&entry.name) { continue };
+        let entry_path = path.join(&entry.name);
+        if entry.is_dir {
+            recursive_tar_append(storage, builder, entry_path, tar_path.join(&entry.name))?;
+        } else {
+            // Streamed straight from the storage rather than read into memory
+            let size = storage.file_len(&entry_path)?;
+            let mut reader = ExactReader(storage.open_read(&entry_path)?.take(size));
+
+            let mut header = Header::new_gnu();
+            header.set_size(size);
+            header.set_mode(0o644);
+            header.set_mtime(SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0));
+            builder.append_data(&mut header, tar_path.join(&entry.name), &mut reader)?;
         }
     };
     
     Ok(())
 }
 
+/// Reads exactly as many bytes as it's limit, failing if the inner reader ends first (so a tar entry always matches it's header)
+struct ExactReader<R>(io::Take<R>);
+
+impl<R: Read> Read for ExactReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.0.read(buf)?;
+        if read == 0 && !buf.is_empty() && self.0.limit() > 0 { return Err(io::ErrorKind::UnexpectedEof.into()) };
+        Ok(read)
+    }
+}
+
 pub fn unpack_tar(path: impl AsRef<Path>, dir_path: impl AsRef<Path>) -> Result<(), io::Error> {
     let tar = File::open(path)?;
     let mut archive = tar::Archive::new(tar);
@@ -81,4 +105,213 @@ pub fn decompress_file(path: impl AsRef<Path>, out_path: impl AsRef<Path>) -> Re
 
     out.flush()?;
     Ok(())
-}
\ No newline at end of file
+}
+/* Indexed archive format
+ * (all numbers are big-endian)
+ * - `INDEXED_MAGIC`
+ * - independently compressed lz4 blocks; one for each file
+ * - index: entry count (u64) followed by entries of:
+ *     path length (u16), path (utf8 with `/` separators), is directory (u8), offset (u64), compressed length (u64), size (u64)
+ * - footer: index offset (u64) followed by `INDEXED_MAGIC`
+ */
+
+pub const INDEXED_MAGIC: &[u8; 8] = b"LDBINDEX";
+pub const ENCRYPTED_MAGIC: &[u8; 8] = b"LDBCRYPT";
+const LZ4_MAGIC: &[u8; 4] = &[0x04, 0x22, 0x4D, 0x18];
+
+/// A file or directory within an indexed archive
+pub struct IndexEntry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub offset: u64,
+    pub length: u64,
+    pub size: u64,
+}
+
+/// Finds the format of a compiled archive from its magic bytes
+pub fn detect_format(path: impl AsRef<Path>) -> Result<crate::ArchiveFormat, io::Error> {
+    let mut magic = [0u8; 8];
+    let read = File::open(path)?.read(&mut magic)?;
+    if read == 8 && &magic == INDEXED_MAGIC { return Ok(crate::ArchiveFormat::Indexed) };
+    if read == 8 && &magic == ENCRYPTED_MAGIC { return Err(io::Error::new(io::ErrorKind::InvalidData, "Archive is encrypted")) };
+    if read >= 4 && &magic[..4] == LZ4_MAGIC { return Ok(crate::ArchiveFormat::TarLz4) };
+    Err(io::Error::new(io::ErrorKind::InvalidData, "Unrecognised archive format"))
+}
+
+/// Checks if a file could have been written by `lazy-db` (a compiled file, a tarball or an empty file) from it's magic bytes
+pub(crate) fn is_own_file(path: &Path) -> bool {
+    let mut header = [0u8; 512];
+    let read = match File::open(path).and_then(|mut x| read_full(&mut x, &mut header)) {
+        Ok(x) => x,
+        Err(_) => return false,
+    };
+    read == 0
+        || (read >= 8 && (&header[..8] == INDEXED_MAGIC || &header[..8] == ENCRYPTED_MAGIC))
+        || (read >= 4 && &header[..4] == LZ4_MAGIC)
+        || (read >= 262 && &header[257..262] == b"ustar")
+}
+
+/// Reads until the buffer is full or the reader ends, returning how much was read
+pub(crate) fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> Result<usize, io::Error> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..])? {
+            0 => break,
+            x => filled += x,
+        }
+    }
+    Ok(filled)
+}
+
+pub fn build_indexed(storage: &dyn Storage, path: impl AsRef<Path>, out_path: impl AsRef<Path>) -> Result<(), io::Error> {
+    let mut out = io::BufWriter::new(File::create(out_path)?);
+    out.write_all(INDEXED_MAGIC)?;
+
+    let mut index = Vec::new();
+    let mut offset = INDEXED_MAGIC.len() as u64;
+    let mut buffers = (Vec::new(), Vec::new());
+    recursive_indexed_append(storage, &mut out, &mut index, &mut offset, &mut buffers, path.as_ref(), PathBuf::new())?;
+
+    // Write index and footer
+    out.write_all(&(index.len() as u64).to_be_bytes())?;
+    for entry in index.iter() {
+        let path = entry.path.components()
+            .map(|x| x.as_os_str().to_str().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Path '{}' isn't valid utf8", entry.path.to_string_lossy()))))
+            .collect::<Result<Vec<_>, _>>()?
+            .join("/");
+        let len = u16::try_from(path.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("Path '{path}' is too long for an indexed archive")))?;
+        out.write_all(&len.to_be_bytes())?;
+        out.write_all(path.as_bytes())?;
+        out.write_all(&[entry.is_dir as u8])?;
+        out.write_all(&entry.offset.to_be_bytes())?;
+        out.write_all(&entry.length.to_be_bytes())?;
+        out.write_all(&entry.size.to_be_bytes())?;
+    }
+    out.write_all(&offset.to_be_bytes())?;
+    out.write_all(INDEXED_MAGIC)?;
+
+    out.into_inner().map_err(|x| x.into_error())?;
+    Ok(())
+}
+
+/// Appends every file within a directory as it's own lz4 block, reusing the same buffers for each file (a block is only compressed whole)
+fn recursive_indexed_append(storage: &dyn Storage, out: &mut impl Write, index: &mut Vec<IndexEntry>, offset: &mut u64, buffers: &mut (Vec<u8>, Vec<u8>), path: &Path, archive_path: PathBuf) -> Result<(), io::Error> {
+    for entry in storage.read_dir(path)? {
+        if is_transient(&entry.name) { continue };
+        let entry_path = path.join(&entry.name);
+        let archive_path = archive_path.join(&entry.name);
+        if entry.is_dir {
+            index.push(IndexEntry { path: archive_path.clone(), is_dir: true, offset: 0, length: 0, size: 0 });
+            recursive_indexed_append(storage, out, index, offset, buffers, &entry_path, archive_path)?;
+        } else {
+            let (bytes, block) = buffers;
+            let size = storage.file_len(&entry_path)?;
+            bytes.clear();
+            bytes.reserve(size as usize);
+            ExactReader(storage.open_read(&entry_path)?.take(size)).read_to_end(bytes)?;
+            block.resize(lz4_flex::block::get_maximum_output_size(bytes.len()), 0);
+            let length = lz4_flex::block::compress_into(bytes, block)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            out.write_all(&block[..length])?;
+
+            index.push(IndexEntry { path: archive_path, is_dir: false, offset: *offset, length: length as u64, size });
+            *offset += length as u64;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the index of an indexed archive
+pub fn read_index(file: &mut File) -> Result<Vec<IndexEntry>, io::Error> {
+    fn invalid() -> io::Error { io::Error::new(io::ErrorKind::InvalidData, "Invalid indexed archive") }
+    fn read_u64(reader: &mut impl Read) -> Result<u64, io::Error> {
+        let mut bytes = [0u8; 8];
+        reader.read_exact(&mut bytes)?;
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    // Read footer
+    let footer_len = 8 + INDEXED_MAGIC.len() as u64;
+    let file_len = file.metadata()?.len();
+    if file_len < INDEXED_MAGIC.len() as u64 + footer_len { return Err(invalid()) };
+    file.seek(SeekFrom::End(-(footer_len as i64)))?;
+    let index_offset = read_u64(file)?;
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic)?;
+    if &magic != INDEXED_MAGIC { return Err(invalid()) };
+
+    // Read entries (the smallest entry is 27 bytes, which bounds the count)
+    let index_end = file_len - footer_len;
+    if index_offset < INDEXED_MAGIC.len() as u64 || index_offset > index_end - 8 { return Err(invalid()) };
+    file.seek(SeekFrom::Start(index_offset))?;
+    let mut reader = io::BufReader::new(file);
+    let count = read_u64(&mut reader)?;
+    if count > (index_end - index_offset - 8) / 27 { return Err(invalid()) };
+    let mut index = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        let mut path = vec![0u8; u16::from_be_bytes(len) as usize];
+        reader.read_exact(&mut path)?;
+        let path = String::from_utf8(path).map_err(|_| invalid())?;
+        let mut is_dir = [0u8];
+        reader.read_exact(&mut is_dir)?;
+
+        let entry = IndexEntry {
+            path: PathBuf::from(path),
+            is_dir: is_dir[0] != 0,
+            offset: read_u64(&mut reader)?,
+            length: read_u64(&mut reader)?,
+            size: read_u64(&mut reader)?,
+        };
+
+        // Refuse entries that would escape the root, or blocks outside of the archive's data
+        if entry.path.components().any(|x| !matches!(x, std::path::Component::Normal(_))) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Invalid archive path '{}'", entry.path.to_string_lossy())));
+        }
+        if !entry.is_dir && (entry.offset < INDEXED_MAGIC.len() as u64 || entry.offset.checked_add(entry.length).is_none_or(|x| x > index_offset)) {
+            return Err(invalid());
+        }
+        index.push(entry);
+    }
+
+    Ok(index)
+}
+
+/// The most an lz4 block can expand by when decompressed
+const MAX_LZ4_RATIO: u64 = 255;
+
+/// Reads and decompresses a single file's block out of an indexed archive
+/// 
+/// Returns `io::ErrorKind::InvalidData` if the block doesn't fit within the archive, or decompresses to more than lz4 ever could.
+pub fn read_indexed_block(file: &mut File, offset: u64, length: u64, size: u64) -> Result<Vec<u8>, io::Error> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid indexed archive block");
+    let file_len = file.metadata()?.len();
+    if offset.checked_add(length).is_none_or(|x| x > file_len) { return Err(invalid()) };
+    if size > length.saturating_mul(MAX_LZ4_RATIO) { return Err(invalid()) };
+    let mut block = vec![0u8; length as usize];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut block)?;
+    lz4_flex::block::decompress(&block, size as usize)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn unpack_indexed(path: impl AsRef<Path>, dir_path: impl AsRef<Path>) -> Result<(), io::Error> {
+    let mut file = File::open(path)?;
+    let dir_path = dir_path.as_ref();
+    fs::create_dir_all(dir_path)?;
+
+    for entry in read_index(&mut file)? {
+        let out_path = dir_path.join(&entry.path);
+        if entry.is_dir {
+            fs::create_dir_all(out_path)?;
+        } else {
+            if let Some(parent) = out_path.parent() { fs::create_dir_all(parent)? };
+            fs::write(out_path, read_indexed_block(&mut file, entry.offset, entry.length, entry.size)?)?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,6 +1,19 @@
+mod entry;
+mod walk;
+mod key;
+mod path;
+mod transfer;
+mod read_only;
+pub use entry::*;
+pub use walk::*;
+pub use key::*;
+pub use transfer::*;
+pub use read_only::*;
+
 use crate::*;
+use crate::storage::{Storage, FileStorage};
 use std::path::{Path, PathBuf};
-use std::fs;
+use std::sync::Arc;
 
 /// Used for reading from a `LazyContainer` with less boiler-plate
 #[macro_export]
@@ -38,7 +51,7 @@ macro_rules! search_container {
             $(let container = container.read_container(stringify!($con))?;)?
             $(let container = container.read_container($can)?;)?
         )*
-        let result: Result<LazyContainer, LDBError> = Ok(container);
+        let result: Result<_, LDBError> = Ok(container);
         result
     })()};
 
@@ -58,21 +71,29 @@ macro_rules! search_container {
 }
 
 /// A wrapper for a directory that holds individual `LazyData` files
+#[derive(Clone)]
 pub struct LazyContainer {
     path: PathBuf,
+    storage: Arc<dyn Storage>,
 }
 
 impl LazyContainer {
     /// Initialises a new, empty `LazyContainer` at the specified path.
     pub fn init(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        Self::init_in(Arc::new(FileStorage::default()), path)
+    }
+
+    /// Initialises a new, empty `LazyContainer` at the specified path within a `Storage`.
+    pub fn init_in(storage: Arc<dyn Storage>, path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
         let path = path.as_ref();
 
         // Checks if path exists or not
-        if !path.is_dir() { fs::create_dir_all(path)? };
+        if !storage.is_dir(path) { storage.create_dir_all(path)? };
         
         // Constructs self
         Ok(Self {
             path: path.to_path_buf(),
+            storage,
         })
     }
 
@@ -80,74 +101,93 @@ impl LazyContainer {
     /// 
     /// Will throw an error if the directory doesn't exist or there is an `io::Error`.
     pub fn load(path: impl AsRef<Path>) -> Result<Self, LDBError> {
+        Self::load_in(Arc::new(FileStorage::default()), path)
+    }
+
+    /// Loads a pre-existing `LazyContainer` directory at a specified path within a `Storage`.
+    /// 
+    /// Will throw an error if the directory doesn't exist or there is an `io::Error`.
+    pub fn load_in(storage: Arc<dyn Storage>, path: impl AsRef<Path>) -> Result<Self, LDBError> {
         let path = path.as_ref().to_path_buf();
 
         // Checks if path exists or not
-        if !path.is_dir() { return Err(LDBError::DirNotFound(path)) };
+        if !storage.is_dir(&path) { return Err(LDBError::DirNotFound(path)) };
 
         // Constructs self
         Ok(Self {
             path,
+            storage,
         })
     }
 
-    /// Generates a `FileWrapper` in write mode from a key (like a relative file path)
+    /// Validates and escapes a key into the path of the item it addresses within this container
+    fn key_path(&self, key: impl AsRef<str>) -> Result<PathBuf, LDBError> {
+        Ok(self.path.join(escape_key(key.as_ref())?))
+    }
+
+    /// Removes the file or directory at a (full) path
+    fn remove_item(&self, path: &Path) -> Result<(), LDBError> {
+        let result = if self.storage.is_dir(path) {
+            self.storage.remove_dir_all(path)
+        } else {
+            self.storage.remove_file(path)
+        };
+        unwrap_result!((result) err => LDBError::IOError(err));
+        Ok(())
+    }
+
+    /// Generates a `LazyWriter` from a key
     /// 
-    /// If the data already exists, it will try to remove it
-    pub fn data_writer(&self, key: impl AsRef<Path>) -> Result<FileWrapper, LDBError> {
-        let path = self.path.join(key);
-        if path.is_file() { let _ = fs::remove_file(&path); }; // if files exists try remove it
-        let file = unwrap_result!((fs::File::create(path)) err => LDBError::IOError(err));
-        Ok(FileWrapper::new_writer(file))
+    /// If the data already exists, it is only replaced once the writer is finished
+    pub fn data_writer(&self, key: impl AsRef<str>) -> Result<LazyWriter, LDBError> {
+        let path = self.key_path(key)?;
+        let writer = unwrap_result!((self.storage.open_write(&path)) err => LDBError::IOError(err));
+        Ok(LazyWriter::from_boxed(writer))
     }
 
     /// Generates a nested `LazyContainer` within this container
     /// 
     /// If container already exists it will **wipe** and **replace** it.
-    pub fn new_container(&self, key: impl AsRef<Path>) -> Result<LazyContainer, LDBError> {
-        let path = self.path.join(&key);
-        if path.is_dir() { unwrap_result!((fs::remove_dir_all(&path)) err => LDBError::IOError(err)) }; // If exists wipe it
-        Ok(unwrap_result!((LazyContainer::init(path)) err => LDBError::IOError(err)))
+    pub fn new_container(&self, key: impl AsRef<str>) -> Result<LazyContainer, LDBError> {
+        let path = self.key_path(key)?;
+        if self.storage.is_dir(&path) { unwrap_result!((self.storage.remove_dir_all(&path)) err => LDBError::IOError(err)) }; // If exists wipe it
+        Ok(unwrap_result!((LazyContainer::init_in(self.storage.clone(), path)) err => LDBError::IOError(err)))
     }
 
     /// Gets a nested `LazyContainer` within this container
     /// 
     /// If container already exists it will load it
     /// Otherwise it will initialise a new one
-    pub fn child_container(&self, key: impl AsRef<Path>) -> Result<LazyContainer, LDBError> {
-        let path = self.path.join(&key);
-        if path.is_dir() { return self.read_container(key) }; // If exists load instead
-        Ok(unwrap_result!((LazyContainer::init(path)) err => LDBError::IOError(err)))
+    pub fn child_container(&self, key: impl AsRef<str>) -> Result<LazyContainer, LDBError> {
+        let path = self.key_path(&key)?;
+        if self.storage.is_dir(&path) { return self.read_container(key) }; // If exists load instead
+        Ok(unwrap_result!((LazyContainer::init_in(self.storage.clone(), path)) err => LDBError::IOError(err)))
     }
 
     /// Reads nested `LazyData` within this container
-    pub fn read_data(&self, key: impl AsRef<Path>) -> Result<LazyData, LDBError> {
-        let path = self.path.join(key);
-        if !path.is_file() { return Err(LDBError::FileNotFound(path)) };
-        LazyData::load(path)
+    pub fn read_data(&self, key: impl AsRef<str>) -> Result<LazyData, LDBError> {
+        let path = self.key_path(key)?;
+        if !self.storage.is_file(&path) { return Err(LDBError::FileNotFound(path)) };
+        LazyData::load_from(self.storage.as_ref(), path)
     }
 
     /// Reads nexted `LazyContainer` within this container
-    pub fn read_container(&self, key: impl AsRef<Path>) -> Result<LazyContainer, LDBError> {
-        let path = self.path.join(key);
-        if !path.is_dir() { return Err(LDBError::DirNotFound(path)) };
-        LazyContainer::load(path)
+    pub fn read_container(&self, key: impl AsRef<str>) -> Result<LazyContainer, LDBError> {
+        let path = self.key_path(key)?;
+        if !self.storage.is_dir(&path) { return Err(LDBError::DirNotFound(path)) };
+        LazyContainer::load_in(self.storage.clone(), path)
     }
 
     /// Tries to remove item at specified key; returns result
-    pub fn remove(&self, key: impl AsRef<Path>) -> Result<(), std::io::Error> {
-        let path = self.path.join(key);
-        if path.is_dir() {
-            fs::remove_dir_all(path)
-        } else {
-            fs::remove_file(path)
-        }
+    #[inline]
+    pub fn remove(&self, key: impl AsRef<str>) -> Result<(), LDBError> {
+        self.remove_item(&self.key_path(key)?)
     }
 
     /// Tries to wipe container's contents; returns result
     pub fn wipe(&self) -> Result<(), std::io::Error> {
-        fs::remove_dir_all(&self.path)?;
-        fs::create_dir_all(&self.path)
+        self.storage.remove_dir_all(&self.path)?;
+        self.storage.create_dir_all(&self.path)
     }
 
     /// Returns a reference to the container's path
@@ -155,4 +195,10 @@ impl LazyContainer {
     pub fn path(&self) -> &Path {
         &self.path
     }
-}
\ No newline at end of file
+
+    /// Returns a reference to the `Storage` the container is stored within
+    #[inline]
+    pub fn storage(&self) -> &Arc<dyn Storage> {
+        &self.storage
+    }
+}
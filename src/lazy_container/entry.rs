@@ -0,0 +1,102 @@
+use super::*;
+use crate::storage::StorageEntry;
+use crate::lazy_database::is_internal;
+
+/// An item nested directly within a `LazyContainer`
+pub enum Entry {
+    Data(LazyData),
+    Container(LazyContainer),
+}
+
+impl Entry {
+    /// Returns the key of the entry within it's parent container
+    pub fn key(&self) -> String {
+        let path = match self {
+            Entry::Data(x) => x.path.as_path(),
+            Entry::Container(x) => x.path(),
+        };
+        path.file_name().map(|x| unescape_key(&x.to_string_lossy())).unwrap_or_default()
+    }
+
+    /// Checks if the entry is `LazyData`
+    #[inline]
+    pub fn is_data(&self) -> bool {
+        matches!(self, Entry::Data(_))
+    }
+
+    /// Checks if the entry is a `LazyContainer`
+    #[inline]
+    pub fn is_container(&self) -> bool {
+        matches!(self, Entry::Container(_))
+    }
+}
+
+/// An iterator over the entries of a `LazyContainer` (see `LazyContainer::entries`)
+/// 
+/// Each `LazyData` is only loaded once it is reached.
+pub struct Entries {
+    container: LazyContainer,
+    items: std::vec::IntoIter<StorageEntry>,
+}
+
+impl Iterator for Entries {
+    type Item = Result<Entry, LDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.items.next()?;
+        let key = unescape_key(&item.name.to_string_lossy());
+        Some(if item.is_dir {
+            self.container.read_container(key).map(Entry::Container)
+        } else {
+            self.container.read_data(key).map(Entry::Data)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.items.size_hint()
+    }
+}
+
+impl LazyContainer {
+    /// Lists the items within this container, excluding internal files, sorted by key
+    fn list(&self) -> Result<Vec<StorageEntry>, LDBError> {
+        let mut items = unwrap_result!((self.storage.read_dir(&self.path)) err => LDBError::IOError(err));
+        items.retain(|x| !is_internal(&x.name.to_string_lossy()));
+        items.sort_by_cached_key(|x| unescape_key(&x.name.to_string_lossy()));
+        Ok(items)
+    }
+
+    /// Lists the keys of all the `LazyData` and `LazyContainer`s within this container, sorted
+    pub fn keys(&self) -> Result<Vec<String>, LDBError> {
+        Ok(self.list()?
+            .into_iter()
+            .map(|x| unescape_key(&x.name.to_string_lossy()))
+            .collect())
+    }
+
+    /// Lists the keys of all the `LazyData` within this container, sorted
+    pub fn data_keys(&self) -> Result<Vec<String>, LDBError> {
+        Ok(self.list()?
+            .into_iter()
+            .filter(|x| !x.is_dir)
+            .map(|x| unescape_key(&x.name.to_string_lossy()))
+            .collect())
+    }
+
+    /// Lists the keys of all the nested `LazyContainer`s within this container, sorted
+    pub fn container_keys(&self) -> Result<Vec<String>, LDBError> {
+        Ok(self.list()?
+            .into_iter()
+            .filter(|x| x.is_dir)
+            .map(|x| unescape_key(&x.name.to_string_lossy()))
+            .collect())
+    }
+
+    /// Iterates over all of the `LazyData` and `LazyContainer`s within this container, sorted by key
+    pub fn entries(&self) -> Result<Entries, LDBError> {
+        Ok(Entries {
+            container: self.clone(),
+            items: self.list()?.into_iter(),
+        })
+    }
+}
@@ -0,0 +1,83 @@
+use super::*;
+use crate::lazy_database::{RESERVED_NAMES, is_internal};
+
+/// Checks that a key is allowed to be used for a `LazyContainer` or `LazyData`
+/// 
+/// Keys may be any unicode string except for:
+/// - empty keys
+/// - the names of `lazy-db`'s own files (like `.meta` or `.lock`)
+/// - keys starting with `/` (absolute paths)
+/// - keys containing a `..` segment between slashes
+/// - keys containing `NUL`
+pub fn validate_key(key: &str) -> Result<(), LDBError> {
+    let invalid = |reason: &str| Err(LDBError::InvalidKey(key.to_string(), reason.to_string()));
+    if key.is_empty() { return invalid("keys cannot be empty") };
+    if RESERVED_NAMES.contains(&key) { return invalid("the name of an internal file is reserved") };
+    if key.starts_with('/') { return invalid("keys cannot be absolute paths") };
+    if key.split('/').any(|x| x == "..") { return invalid("keys cannot contain '..' segments") };
+    if key.contains('\0') { return invalid("keys cannot contain NUL") };
+    Ok(())
+}
+
+/// Validates and escapes a key into the file name it is stored as
+/// 
+/// `%`, `/` and `\` are percent-encoded so that any key is a single file name, as is a leading `.` so that
+/// only internal files have names starting with `.`.
+pub fn escape_key(key: &str) -> Result<String, LDBError> {
+    validate_key(key)?;
+    let escaped = percent_encode(key, &['/', '\\']);
+    Ok(match escaped.strip_prefix('.') {
+        Some(rest) => format!("%2E{rest}"),
+        None => escaped,
+    })
+}
+
+/// Reverses `escape_key`; names with invalid escape sequences (like ones stored before keys were escaped) are kept as they are
+pub fn unescape_key(name: &str) -> String {
+    percent_decode(name).unwrap_or_else(|| name.to_string())
+}
+
+/// Lists the renames (in order) that give the items within a directory (and it's containers) stored by older versions, before keys were escaped, their escaped names
+/// 
+/// Each container's items come before it, and longer names before shorter ones; an escaped name is always longer than the key it escapes,
+/// so no item takes the place of one that's still to be renamed.
+pub(crate) fn legacy_renames(storage: &dyn Storage, dir: &Path) -> Result<Vec<(PathBuf, PathBuf)>, LDBError> {
+    let mut items = unwrap_result!((storage.read_dir(dir)) err => LDBError::IOError(err));
+    items.retain(|x| !is_internal(&x.name.to_string_lossy()));
+    items.sort_by_key(|x| std::cmp::Reverse(x.name.len()));
+
+    let mut renames = Vec::new();
+    for item in items.iter().filter(|x| x.is_dir) { renames.extend(legacy_renames(storage, &dir.join(&item.name))?) };
+    for item in items {
+        let Some(key) = item.name.to_str() else { continue }; // Can't be addressed by any key
+        let escaped = escape_key(key)?;
+        if escaped != key { renames.push((dir.join(key), dir.join(escaped))) };
+    }
+    Ok(renames)
+}
+
+/// Percent-encodes `%` and the (ascii) reserved characters as uppercase hex; the one escaping scheme used for both stored names and `LazyPath`s
+pub(crate) fn percent_encode(key: &str, reserved: &[char]) -> String {
+    let mut escaped = String::with_capacity(key.len());
+    for c in key.chars() {
+        if c == '%' || reserved.contains(&c) {
+            escaped.push_str(&format!("%{:02X}", c as u32));
+        } else { escaped.push(c) };
+    }
+    escaped
+}
+
+/// Reverses `percent_encode` (hex in either case); returns `None` if there is an invalid escape sequence
+pub(crate) fn percent_decode(name: &str) -> Option<String> {
+    let mut key = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(i) = rest.find('%') {
+        key.push_str(&rest[..i]);
+        let hex = rest.get(i + 1..i + 3).filter(|x| x.bytes().all(|x| x.is_ascii_hexdigit()))?;
+        let byte = u8::from_str_radix(hex, 16).ok().filter(u8::is_ascii)?;
+        key.push(byte as char);
+        rest = &rest[i + 3..];
+    }
+    key.push_str(rest);
+    Some(key)
+}
@@ -0,0 +1,73 @@
+use super::*;
+
+impl LazyContainer {
+    /// Reads the nested `LazyContainer` addressed by a path relative to this container
+    /// 
+    /// Never creates anything; returns `LDBError::DirNotFound` for the first container along the path that doesn't exist
+    pub fn get_container(&self, path: impl IntoLazyPath) -> Result<LazyContainer, LDBError> {
+        let path = path.into_lazy_path()?;
+        let mut container = self.clone();
+        for key in path.containers() {
+            container = container.read_container(key)?;
+        }
+        match path.data() {
+            Some(_) => Err(LDBError::InvalidPath(path.to_string(), String::from("expected a container path"))),
+            None => Ok(container),
+        }
+    }
+
+    /// Gets the nested `LazyContainer` addressed by a path relative to this container, creating any containers along it that don't exist yet
+    pub fn get_or_create(&self, path: impl IntoLazyPath) -> Result<LazyContainer, LDBError> {
+        let path = path.into_lazy_path()?;
+        if path.is_data() { return Err(LDBError::InvalidPath(path.to_string(), String::from("expected a container path"))) };
+        let mut container = self.clone();
+        for key in path.containers() {
+            container = container.child_container(key)?;
+        }
+        Ok(container)
+    }
+
+    /// Reads the nested `LazyData` addressed by a path relative to this container
+    /// 
+    /// Never creates anything; returns `LDBError::DirNotFound` for the first container along the path that doesn't exist
+    pub fn get(&self, path: impl IntoLazyPath) -> Result<LazyData, LDBError> {
+        let path = path.into_lazy_path()?;
+        let data = match path.data() {
+            Some(x) => x,
+            None => return Err(LDBError::InvalidPath(path.to_string(), String::from("expected a data path"))),
+        };
+        let mut container = self.clone();
+        for key in path.containers() {
+            container = container.read_container(key)?;
+        }
+        container.read_data(data)
+    }
+
+    /// Writes the nested `LazyData` addressed by a path relative to this container with a function like `LazyData::new_u8`
+    /// 
+    /// Any missing containers along the path are created.
+    /// ```rust
+    /// use lazy_db::*;
+    /// let database = LazyDB::in_memory().unwrap();
+    /// database.set("/people/Dave::age", |file| LazyData::new_u8(file, 21)).unwrap();
+    /// ```
+    pub fn set(&self, path: impl IntoLazyPath, f: impl FnOnce(LazyWriter) -> Result<(), LDBError>) -> Result<(), LDBError> {
+        let path = path.into_lazy_path()?;
+        let data = match path.data() {
+            Some(x) => x,
+            None => return Err(LDBError::InvalidPath(path.to_string(), String::from("expected a data path"))),
+        };
+        let container = self.get_or_create(path.parent().unwrap_or_default())?;
+        f(container.data_writer(data)?)
+    }
+
+    /// Removes the nested `LazyContainer` or `LazyData` addressed by a path relative to this container
+    pub fn remove_path(&self, path: impl IntoLazyPath) -> Result<(), LDBError> {
+        let path = path.into_lazy_path()?;
+        let parent = match path.parent() {
+            Some(x) => self.get_container(x)?,
+            None => return Err(LDBError::InvalidPath(path.to_string(), String::from("cannot remove the root container"))),
+        };
+        parent.remove(path.key().unwrap_or_default())
+    }
+}
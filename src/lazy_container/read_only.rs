@@ -0,0 +1,66 @@
+use super::*;
+
+/// A `LazyContainer` that can only be read from
+/// 
+/// It has no way to write, remove or wipe anything, and every nested container it reads is read-only too.
+#[derive(Clone)]
+pub struct ReadOnlyContainer {
+    container: LazyContainer,
+}
+
+impl From<LazyContainer> for ReadOnlyContainer {
+    #[inline]
+    fn from(container: LazyContainer) -> Self {
+        Self { container }
+    }
+}
+
+impl ReadOnlyContainer {
+    /// Reads nested `LazyData` within this container
+    #[inline]
+    pub fn read_data(&self, key: impl AsRef<str>) -> Result<LazyData, LDBError> {
+        self.container.read_data(key)
+    }
+
+    /// Reads nested `LazyContainer` within this container
+    #[inline]
+    pub fn read_container(&self, key: impl AsRef<str>) -> Result<ReadOnlyContainer, LDBError> {
+        self.container.read_container(key).map(Self::from)
+    }
+
+    /// Reads the nested `LazyData` addressed by a path relative to this container
+    #[inline]
+    pub fn get(&self, path: impl IntoLazyPath) -> Result<LazyData, LDBError> {
+        self.container.get(path)
+    }
+
+    /// Reads the nested `LazyContainer` addressed by a path relative to this container
+    #[inline]
+    pub fn get_container(&self, path: impl IntoLazyPath) -> Result<ReadOnlyContainer, LDBError> {
+        self.container.get_container(path).map(Self::from)
+    }
+
+    /// Lists the keys of all the `LazyData` and `LazyContainer`s within this container, sorted
+    #[inline]
+    pub fn keys(&self) -> Result<Vec<String>, LDBError> {
+        self.container.keys()
+    }
+
+    /// Lists the keys of all the `LazyData` within this container, sorted
+    #[inline]
+    pub fn data_keys(&self) -> Result<Vec<String>, LDBError> {
+        self.container.data_keys()
+    }
+
+    /// Lists the keys of all the nested `LazyContainer`s within this container, sorted
+    #[inline]
+    pub fn container_keys(&self) -> Result<Vec<String>, LDBError> {
+        self.container.container_keys()
+    }
+
+    /// Returns a reference to the container's path
+    #[inline]
+    pub fn path(&self) -> &Path {
+        self.container.path()
+    }
+}
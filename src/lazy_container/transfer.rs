@@ -0,0 +1,138 @@
+use super::*;
+use std::io;
+
+/// Suffix of the name an item is set aside under (after a leading `.`) while it's replaced by a transfer
+pub(crate) const REPLACED_SUFFIX: &str = ".replaced";
+
+/// What to do when the destination of a rename, move or copy already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overwrite {
+    /// Fail with `LDBError::AlreadyExists`
+    #[default]
+    Deny,
+    /// Remove the existing item and replace it
+    Replace,
+    /// Leave the existing item alone and do nothing
+    Skip,
+}
+
+impl LazyContainer {
+    /// Renames the `LazyData` or `LazyContainer` at a key within this container
+    /// 
+    /// Atomic if the destination doesn't exist (or is replaced `LazyData`) and the container is stored on a single filesystem.
+    #[inline]
+    pub fn rename(&self, from: impl AsRef<str>, to: impl AsRef<str>, overwrite: Overwrite) -> Result<(), LDBError> {
+        self.move_to(from, self, to, overwrite)
+    }
+
+    /// Moves the `LazyData` or `LazyContainer` at a key within this container to a new key within another container
+    /// 
+    /// Within the same `Storage` the item is renamed in place; otherwise it is copied over and then removed.
+    /// A replaced container is only removed once the item has taken it's place.
+    pub fn move_to(&self, key: impl AsRef<str>, other: &LazyContainer, new_key: impl AsRef<str>, overwrite: Overwrite) -> Result<(), LDBError> {
+        let from = self.key_path(key)?;
+        let to = other.key_path(new_key)?;
+        if from == to && Arc::ptr_eq(&self.storage, &other.storage) { return Ok(()) };
+        self.check_nesting(&from, other, &to, "move")?;
+        let replaced = match self.prepare_transfer(&from, other, &to, overwrite)? {
+            Destination::Skip => return Ok(()),
+            Destination::Free => None,
+            Destination::Replaced(x) => Some(x),
+        };
+
+        let same_storage = Arc::ptr_eq(&self.storage, &other.storage);
+        let result = if same_storage { self.storage.rename(&from, &to) }
+            else { copy_item(self.storage.as_ref(), &from, other.storage.as_ref(), &to) };
+        other.finish_transfer(&to, replaced, result)?;
+        if !same_storage { self.remove_item(&from)? };
+        Ok(())
+    }
+
+    /// Copies the `LazyData` or `LazyContainer` (and everything nested within it) at a key within this container to a new key within another container
+    pub fn copy_tree(&self, key: impl AsRef<str>, dest: &LazyContainer, new_key: impl AsRef<str>, overwrite: Overwrite) -> Result<(), LDBError> {
+        let from = self.key_path(key)?;
+        let to = dest.key_path(new_key)?;
+        self.check_nesting(&from, dest, &to, "copy")?;
+        let replaced = match self.prepare_transfer(&from, dest, &to, overwrite)? {
+            Destination::Skip => return Ok(()),
+            Destination::Free => None,
+            Destination::Replaced(x) => Some(x),
+        };
+        dest.finish_transfer(&to, replaced, copy_item(self.storage.as_ref(), &from, dest.storage.as_ref(), &to))
+    }
+
+    /// Checks that neither the source nor the destination is within the other (before anything is replaced)
+    fn check_nesting(&self, from: &Path, dest: &LazyContainer, to: &Path, action: &str) -> Result<(), LDBError> {
+        if !Arc::ptr_eq(&self.storage, &dest.storage) { return Ok(()) };
+        let reason = if to.starts_with(from) {
+            format!("cannot {action} a container into itself")
+        } else if from.starts_with(to) {
+            format!("cannot {action} an item onto a container it's within")
+        } else { return Ok(()) };
+        Err(LDBError::InvalidPath(to.to_string_lossy().to_string(), reason))
+    }
+
+    /// Checks the source exists and applies the overwrite policy to the destination
+    fn prepare_transfer(&self, from: &Path, dest: &LazyContainer, to: &Path, overwrite: Overwrite) -> Result<Destination, LDBError> {
+        if !self.storage.is_file(from) && !self.storage.is_dir(from) { return Err(LDBError::FileNotFound(from.to_path_buf())) };
+        if !dest.storage.is_file(to) && !dest.storage.is_dir(to) { return Ok(Destination::Free) };
+
+        match overwrite {
+            Overwrite::Deny => Err(LDBError::AlreadyExists(to.to_path_buf())),
+            Overwrite::Skip => Ok(Destination::Skip),
+            Overwrite::Replace => {
+                // Files are replaced by the rename itself (atomically) when they can be
+                let same_kind_file = self.storage.is_file(from) && dest.storage.is_file(to);
+                if same_kind_file && Arc::ptr_eq(&self.storage, &dest.storage) { return Ok(Destination::Free) };
+
+                // Anything else is set aside until it's replacement is in place
+                let name = to.file_name().unwrap_or_default().to_string_lossy();
+                let replaced = to.with_file_name(format!(".{name}{REPLACED_SUFFIX}"));
+                if dest.storage.is_file(&replaced) || dest.storage.is_dir(&replaced) { dest.remove_item(&replaced)? };
+                unwrap_result!((dest.storage.rename(to, &replaced)) err => LDBError::IOError(err));
+                Ok(Destination::Replaced(replaced))
+            },
+        }
+    }
+
+    /// Removes the item a transfer replaced once it succeeded, or puts it back if it failed
+    fn finish_transfer(&self, to: &Path, replaced: Option<PathBuf>, result: io::Result<()>) -> Result<(), LDBError> {
+        let Some(replaced) = replaced else {
+            unwrap_result!((result) err => LDBError::IOError(err));
+            return Ok(());
+        };
+
+        if let Err(e) = result {
+            if self.storage.is_file(to) || self.storage.is_dir(to) { let _ = self.remove_item(to); }
+            let _ = self.storage.rename(&replaced, to);
+            return Err(LDBError::IOError(e));
+        }
+        self.remove_item(&replaced)
+    }
+}
+
+/// What a transfer does with it's destination
+enum Destination {
+    /// Nothing is there (or it's replaced by the transfer itself)
+    Free,
+    /// Something is there and is kept
+    Skip,
+    /// Something was there and was set aside to a path, until the transfer finishes
+    Replaced(PathBuf),
+}
+
+/// Recursively copies a file or directory from one `Storage` to another
+pub(crate) fn copy_item(from_storage: &dyn Storage, from: &Path, to_storage: &dyn Storage, to: &Path) -> io::Result<()> {
+    if from_storage.is_dir(from) {
+        to_storage.create_dir_all(to)?;
+        for entry in from_storage.read_dir(from)? {
+            copy_item(from_storage, &from.join(&entry.name), to_storage, &to.join(&entry.name))?;
+        }
+        return Ok(());
+    }
+
+    let mut reader = from_storage.open_read(from)?;
+    let mut writer = to_storage.open_write(to)?;
+    io::copy(&mut reader, &mut writer)?;
+    writer.commit()
+}
@@ -0,0 +1,99 @@
+use super::*;
+
+/// Hooks for a recursive traversal of a `LazyContainer` (see `LazyContainer::visit`)
+/// 
+/// All of the hooks do nothing by default.
+#[allow(unused_variables)]
+pub trait Visitor {
+    /// Called on a container before any of it's items are visited
+    fn enter_container(&mut self, path: &LazyPath, container: &LazyContainer) -> Result<(), LDBError> {
+        Ok(())
+    }
+
+    /// Called on each `LazyData` within the tree
+    fn visit_data(&mut self, path: &LazyPath, data: LazyData) -> Result<(), LDBError> {
+        Ok(())
+    }
+
+    /// Called on a container after all of it's items have been visited
+    fn leave_container(&mut self, path: &LazyPath, container: &LazyContainer) -> Result<(), LDBError> {
+        Ok(())
+    }
+}
+
+/// A depth-first iterator over all of the items nested within a `LazyContainer` (see `LazyContainer::walk`)
+/// 
+/// Containers are yielded before the items within them, with paths relative to the walked container.
+pub struct Walk {
+    stack: Vec<(LazyPath, Entries)>,
+    max_depth: usize,
+}
+
+impl Walk {
+    /// Limits how deeply nested the yielded items may be (items directly within the walked container have a depth of 1)
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+}
+
+impl Iterator for Walk {
+    type Item = Result<(LazyPath, Entry), LDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (parent, entries) = self.stack.last_mut()?;
+            let entry = match entries.next() {
+                Some(Ok(x)) => x,
+                Some(Err(e)) => return Some(Err(e)),
+                None => { self.stack.pop(); continue },
+            };
+
+            let path = match &entry {
+                Entry::Container(_) => parent.join_container(entry.key()),
+                Entry::Data(_) => parent.join_data(entry.key()),
+            };
+            if path.depth() > self.max_depth { continue };
+
+            // Descend into containers
+            if let Entry::Container(container) = &entry {
+                if path.depth() < self.max_depth {
+                    match container.entries() {
+                        Ok(x) => self.stack.push((path.clone(), x)),
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+            }
+
+            return Some(Ok((path, entry)));
+        }
+    }
+}
+
+impl LazyContainer {
+    /// Walks depth-first through every item nested within this container, sorted by key at each level
+    pub fn walk(&self) -> Result<Walk, LDBError> {
+        Ok(Walk {
+            stack: vec![(LazyPath::root(), self.entries()?)],
+            max_depth: usize::MAX,
+        })
+    }
+
+    /// Recursively traverses this container with a `Visitor` (this container has the root path)
+    pub fn visit(&self, visitor: &mut impl Visitor) -> Result<(), LDBError> {
+        self.visit_at(&LazyPath::root(), visitor)
+    }
+
+    fn visit_at(&self, path: &LazyPath, visitor: &mut impl Visitor) -> Result<(), LDBError> {
+        visitor.enter_container(path, self)?;
+        for entry in self.entries()? {
+            let entry = entry?;
+            let key = entry.key();
+            match entry {
+                Entry::Container(x) => x.visit_at(&path.join_container(key), visitor)?,
+                Entry::Data(x) => visitor.visit_data(&path.join_data(key), x)?,
+            }
+        }
+        visitor.leave_container(path, self)
+    }
+}
@@ -1,30 +1,37 @@
 mod reading;
 mod writing;
-mod file_wrapper;
+mod reader;
+mod writer;
 
-pub use reading::*;
-pub use writing::*;
-pub use file_wrapper::*;
+pub use reader::*;
+pub use writer::*;
 
 use std::path::{Path, PathBuf};
 use crate::*;
+use crate::storage::{Storage, FileStorage};
 
 pub struct LazyData {
     pub path: PathBuf,
     pub lazy_type: LazyType,
-    wrapper: FileWrapper,
+    reader: LazyReader,
 }
 
 impl LazyData {
+    /// Loads a `LazyData` file from the filesystem
     pub fn load(path: impl AsRef<Path>) -> Result<Self, LDBError> {
+        Self::load_from(&FileStorage::default(), path)
+    }
+
+    /// Loads a `LazyData` file from a `Storage`
+    pub fn load_from(storage: &dyn Storage, path: impl AsRef<Path>) -> Result<Self, LDBError> {
         let path = path.as_ref();
 
         // Check for the existance of the path and if it's a file
-        if !path.is_file() { return Err(LDBError::FileNotFound(path.to_path_buf())) };
+        if !storage.is_file(path) { return Err(LDBError::FileNotFound(path.to_path_buf())) };
 
         // Get the reader
         let mut reader =
-            FileWrapper::new_reader(unwrap_result!((std::fs::File::open(path)) err => LDBError::IOError(err)));
+            LazyReader::from_boxed(unwrap_result!((storage.open_read(path)) err => LDBError::IOError(err)));
 
         // Reads the byte repr of it's `LazyType`
         let lazy_type =
@@ -33,7 +40,7 @@ impl LazyData {
         Ok(Self {
             path: path.to_path_buf(),
             lazy_type,
-            wrapper: reader,
+            reader,
         })
     }
 
@@ -0,0 +1,47 @@
+use super::*;
+use std::io::{Read, BufReader};
+
+/// A buffered reader over the contents of a `LazyData` file
+pub struct LazyReader {
+    reader: BufReader<Box<dyn Read + Send>>,
+}
+
+impl LazyReader {
+    /// Constructs a new `LazyReader` from an opened file (or any other reader)
+    pub fn new(reader: impl Read + Send + 'static) -> Self {
+        Self::from_boxed(Box::new(reader))
+    }
+
+    /// Constructs a new `LazyReader` from a reader opened by a `Storage`
+    pub fn from_boxed(reader: Box<dyn Read + Send>) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+        }
+    }
+
+    /// Reads an exact amount of bytes from the file
+    pub fn read(&mut self, length: usize) -> Result<Box<[u8]>, LDBError> {
+        let mut buffer = vec![0u8; length].into_boxed_slice();
+        unwrap_result!((self.reader.read_exact(&mut buffer)) err => LDBError::IOError(err));
+        Ok(buffer)
+    }
+
+    /// Reads an exact amount of bytes from the file; returns `None` if there aren't enough bytes left
+    pub fn read_opt(&mut self, length: usize) -> Result<Option<Box<[u8]>>, LDBError> {
+        let mut buffer = vec![0u8; length].into_boxed_slice();
+        let mut filled = 0;
+        while filled < length {
+            let read = unwrap_result!((self.reader.read(&mut buffer[filled..])) err => LDBError::IOError(err));
+            if read == 0 { return Ok(None) };
+            filled += read;
+        }
+        Ok(Some(buffer))
+    }
+
+    /// Reads to the end of the file (consumes reader)
+    pub fn read_to_end(mut self) -> Result<Box<[u8]>, LDBError> {
+        let mut buffer = Vec::new();
+        unwrap_result!((self.reader.read_to_end(&mut buffer)) err => LDBError::IOError(err));
+        Ok(buffer.into_boxed_slice())
+    }
+}
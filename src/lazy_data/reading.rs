@@ -22,7 +22,7 @@ macro_rules! collect_number {
             incorrect_type!(self.lazy_type, $lazy_type);
 
             // Expensive and best to be avoided if possible
-            let bytes = self.wrapper.read_to_end()?;
+            let bytes = self.reader.read_to_end()?;
             const LENGTH: usize = <$type>::BITS as usize / 8usize;
 
             // Check if the size is correct
@@ -50,7 +50,7 @@ macro_rules! collect_number {
             incorrect_type!(self.lazy_type, $lazy_type);
 
             // Expensive and best to be avoided if possible
-            let bytes = self.wrapper.read_to_end()?;
+            let bytes = self.reader.read_to_end()?;
             const LENGTH: usize = <$type>::BITS as usize / 8usize;
 
             // Check if the size is correct
@@ -85,13 +85,13 @@ macro_rules! collect_array {
 
             // Read array-type
             let array_type =
-                LazyType::try_from(self.wrapper.read(1)?[0])?;
+                LazyType::try_from(self.reader.read(1)?[0])?;
             incorrect_type!(array_type, LazyType::$lazy_type);
 
             const LENGTH: usize = $bytes;
             let mut result = Vec::<$type>::new();
             loop {
-                let bytes = match self.wrapper.read_opt(LENGTH)? {
+                let bytes = match self.reader.read_opt(LENGTH)? {
                     Some(x) => x,
                     None => break,
                 };
@@ -115,7 +115,7 @@ impl LazyData {
     /// Returns `LDBError::IncorrectType` if the LazyData type is not `LazyType::Binary`
     pub fn collect_binary(self) -> Result<Box<[u8]>, LDBError> {
         incorrect_type!(self.lazy_type, LazyType::Binary);
-        self.wrapper.read_to_end()
+        self.reader.read_to_end()
     }
 
     /// ### Expensive Action
@@ -129,7 +129,7 @@ impl LazyData {
     pub fn collect_string(self) -> Result<String, LDBError> {
         incorrect_type!(self.lazy_type, LazyType::String);
         // Expensive and best to be avoided if possible
-        let bytes = self.wrapper.read_to_end()?;
+        let bytes = self.reader.read_to_end()?;
         
         if let Ok(x) = String::from_utf8(bytes.to_vec()) {
             Ok(x)
@@ -179,7 +179,7 @@ impl LazyData {
         incorrect_type!(self.lazy_type, LazyType::F32);
 
         // Expensive and best to be avoided if possible
-        let bytes = self.wrapper.read_to_end()?;
+        let bytes = self.reader.read_to_end()?;
 
         // Check if the size is correct
         if bytes.len() != 4 {
@@ -204,7 +204,7 @@ impl LazyData {
         incorrect_type!(self.lazy_type, LazyType::F64);
 
         // Expensive and best to be avoided if possible
-        let bytes = self.wrapper.read_to_end()?;
+        let bytes = self.reader.read_to_end()?;
 
         // Check if the size is correct
         if bytes.len() != 8 {
@@ -243,7 +243,7 @@ impl LazyData {
 
         // Loads string as a path
         // Expensive and best to be avoided if possible
-        let bytes = self.wrapper.read_to_end()?;
+        let bytes = self.reader.read_to_end()?;
         
         let string = if let Ok(x) = String::from_utf8(bytes.to_vec()) {
             x
@@ -251,6 +251,13 @@ impl LazyData {
             return Err(LDBError::InvalidUTF8String(bytes))
         };
 
-        database.as_container()?.read_data(string)
+        // Walks the containers separated by `/` up to the data
+        let mut keys: Vec<&str> = string.split('/').collect();
+        let data = keys.pop().unwrap_or_default();
+        let mut container = database.as_container()?;
+        for key in keys {
+            container = container.read_container(key)?;
+        }
+        container.read_data(data)
     }
 }
\ No newline at end of file
@@ -0,0 +1,37 @@
+use super::*;
+use std::io::{Write, BufWriter};
+use crate::storage::StorageWriter;
+
+/// A buffered writer that creates the contents of a `LazyData` file
+///
+/// The writer must be consumed with `LazyWriter::finish` for any errors while flushing to be reported
+pub struct LazyWriter {
+    writer: BufWriter<Box<dyn StorageWriter>>,
+}
+
+impl LazyWriter {
+    /// Constructs a new `LazyWriter` from a created file (or any other `StorageWriter`)
+    pub fn new(writer: impl StorageWriter + 'static) -> Self {
+        Self::from_boxed(Box::new(writer))
+    }
+
+    /// Constructs a new `LazyWriter` from a writer opened by a `Storage`
+    pub fn from_boxed(writer: Box<dyn StorageWriter>) -> Self {
+        Self {
+            writer: BufWriter::new(writer),
+        }
+    }
+
+    /// Writes a byte slice into the file
+    pub fn write(&mut self, bytes: &[u8]) -> Result<(), LDBError> {
+        unwrap_result!((self.writer.write_all(bytes)) err => LDBError::IOError(err));
+        Ok(())
+    }
+
+    /// Flushes all of the buffered bytes and commits them into the file (consumes writer)
+    pub fn finish(self) -> Result<(), LDBError> {
+        let writer = unwrap_result!((self.writer.into_inner()) err => LDBError::IOError(err.into_error()));
+        unwrap_result!((writer.commit()) err => LDBError::IOError(err));
+        Ok(())
+    }
+}
@@ -5,21 +5,21 @@ use super::*;
 macro_rules! new_number {
     (($name:ident) $type:ty = $lazy_type:expr) => {
         /// Creates a new `LazyData` file with an unsigned integer and type
-        pub fn $name(mut file: FileWrapper, value: $type) -> Result<(), LDBError> {
+        pub fn $name(mut file: LazyWriter, value: $type) -> Result<(), LDBError> {
             let bytes = value.to_be_bytes();
             file.write(&[$lazy_type.into()])?;
             file.write(&bytes)?;
-            Ok(())
+            file.finish()
         }
     };
 
     (signed ($name:ident) $type:ty = $lazy_type:expr) => {
         /// Creates a new `LazyData` file with a signed integer and type
-        pub fn $name(mut file: FileWrapper, value: $type) -> Result<(), LDBError> {
+        pub fn $name(mut file: LazyWriter, value: $type) -> Result<(), LDBError> {
             let bytes = value.to_be_bytes();
             file.write(&[$lazy_type.into()])?;
             file.write(&bytes)?;
-            Ok(())
+            file.finish()
         }
     };
 }
@@ -27,30 +27,30 @@ macro_rules! new_number {
 macro_rules! new_array {
     (($name:ident) $type:ty = $lazy_type:ident) => {
         /// Creates a new `LazyData` file with an array type and value
-        pub fn $name(mut file: FileWrapper, value: &[$type]) -> Result<(), LDBError> {
+        pub fn $name(mut file: LazyWriter, value: &[$type]) -> Result<(), LDBError> {
             file.write(&[LazyType::Array.into(), LazyType::$lazy_type.into()])?;
             for i in value {
                 let bytes = i.to_be_bytes();
                 file.write(&bytes)?;
             }
-            Ok(())
+            file.finish()
         }
     }
 }
 
 impl LazyData {
     /// Creates a new `LazyData` file with the type of `LazyType::Void`
-    pub fn new_void(mut file: FileWrapper, _value: ()) -> Result<(), LDBError> {
+    pub fn new_void(mut file: LazyWriter, _value: ()) -> Result<(), LDBError> {
         file.write(&[LazyType::Void.into()])?;
-        Ok(())
+        file.finish()
     }
 
     /// Creates a new `LazyData` file with a `String` value and type
-    pub fn new_string(mut file: FileWrapper, value: &str) -> Result<(), LDBError> {
+    pub fn new_string(mut file: LazyWriter, value: &str) -> Result<(), LDBError> {
         let bytes = value.as_bytes();
         file.write(&[LazyType::String.into()])?;
         file.write(bytes)?;
-        Ok(())
+        file.finish()
     }
 
     // Signed Integers
@@ -84,39 +84,44 @@ impl LazyData {
     /* Floating point numbers */
 
     /// Creates a new `LazyData` file with an `f32` value and type
-    pub fn new_f32(mut file: FileWrapper, value: f32) -> Result<(), LDBError> {
+    pub fn new_f32(mut file: LazyWriter, value: f32) -> Result<(), LDBError> {
         let bytes = value.to_be_bytes();
         file.write(&[LazyType::F32.into()])?;
         file.write(&bytes)?;
-        Ok(())
+        file.finish()
     }
 
     /// Creates a new `LazyData` file with an `f64` value and type
-    pub fn new_f64(mut file: FileWrapper, value: f64) -> Result<(), LDBError> {
+    pub fn new_f64(mut file: LazyWriter, value: f64) -> Result<(), LDBError> {
         let bytes = value.to_be_bytes();
         file.write(&[LazyType::F64.into()])?;
         file.write(&bytes)?;
-        Ok(())
+        file.finish()
     }
 
     /// Creates a new `LazyData` file with a `binary` value and type
-    pub fn new_binary(mut file: FileWrapper, value: &[u8]) -> Result<(), LDBError> {
+    pub fn new_binary(mut file: LazyWriter, value: &[u8]) -> Result<(), LDBError> {
         file.write(&[LazyType::Binary.into()])?;
-        file.write(value)
+        file.write(value)?;
+        file.finish()
     }
 
     /// Creates a new `LazyData` file with a `bool` value and type
-    pub fn new_bool(mut file: FileWrapper, value: bool) -> Result<(), LDBError> {
+    pub fn new_bool(mut file: LazyWriter, value: bool) -> Result<(), LDBError> {
         if value {
-            file.write(&[LazyType::True.into()])
+            file.write(&[LazyType::True.into()])?;
         } else {
-            file.write(&[LazyType::False.into()])
+            file.write(&[LazyType::False.into()])?;
         }
+        file.finish()
     }
 
     /// Creates a new `LazyData` file with a link (it's like a reference) value and type
-    pub fn new_link(mut file: FileWrapper, data: impl AsRef<Path>) -> Result<(), LDBError> {
+    /// 
+    /// The link is the keys of the containers leading up to the data separated by `/` (like `nested/data`)
+    pub fn new_link(mut file: LazyWriter, data: impl AsRef<Path>) -> Result<(), LDBError> {
         file.write(&[LazyType::Link.into()])?;
-        file.write(data.as_ref().as_os_str().as_bytes())
+        file.write(data.as_ref().as_os_str().as_bytes())?;
+        file.finish()
     }
 }
\ No newline at end of file
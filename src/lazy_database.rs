@@ -1,18 +1,43 @@
+mod options;
+mod transaction;
+mod lock;
+mod shared;
+mod read_only;
+mod recovery;
+mod migration;
+mod metadata;
+mod snapshot;
+pub use options::*;
+pub use transaction::*;
+pub use shared::*;
+pub use read_only::*;
+pub use recovery::*;
+pub use migration::*;
+pub use metadata::Metadata;
+pub use lock::LOCK_FILE;
+pub(crate) use snapshot::SNAPSHOTS_DIR;
+use lock::DbLock;
+
 use crate::*;
+use crate::storage::{Storage, FileStorage, MemoryStorage, ArchiveStorage, WalStorage, DirtyStorage, Durability, WAL_FILE};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::fs;
 
 /// Used for reading from a `LazyDB` with less boiler-plate
+/// 
+/// Never creates anything; returns `LDBError::DirNotFound` for the first container along the path that doesn't exist
 #[macro_export]
 macro_rules! search_database {
     (($ldb:expr) /$($($con:ident)?$(($can:expr))?)/ *) => {(|| {
         let database = &$ldb;
         let container = database.as_container()?;
         $(
-            $(let container = container.child_container(stringify!($con))?;)?
-            $(let container = container.child_container($can)?;)?
+            $(let container = container.read_container(stringify!($con))?;)?
+            $(let container = container.read_container($can)?;)?
         )*
-        let result: Result<LazyContainer, LDBError> = Ok(container);
+        let result: Result<_, LDBError> = Ok(container);
         result
     })()};
 
@@ -32,7 +57,9 @@ macro_rules! search_database {
     })()};
 }
 
-/// Used for reading from a `LazyDB` with less boiler-plate
+/// Used for writing to a `LazyDB` with less boiler-plate
+/// 
+/// Creates any containers along the path that don't exist yet
 #[macro_export]
 macro_rules! write_database {
     (($ldb:expr) $($item:ident)?$(($obj:expr))? = $func:ident($value:expr)) => {(|| {
@@ -44,7 +71,12 @@ macro_rules! write_database {
     })()};
 
     (($ldb:expr) /$($($con:ident)?$(($can:expr))?)/ *::$($item:ident)?$(($obj:expr))? = $func:ident($value:expr)) => {(|| {
-        let mut container = search_database!(($ldb) /$($($con)?$(($can))?)/ *)?;
+        let database = &$ldb;
+        let container = database.as_container()?;
+        $(
+            $(let container = container.child_container(stringify!($con))?;)?
+            $(let container = container.child_container($can)?;)?
+        )*
 
         $(LazyData::$func(container.data_writer(stringify!($item))?, $value)?;)?
         $(LazyData::$func(container.data_writer($obj)?, $value)?;)?
@@ -52,12 +84,42 @@ macro_rules! write_database {
     })()}
 }
 
+/// The file format a `LazyDB` is compiled into
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// A tarball compressed as a single lz4 frame; reading any single file means decompressing from the start
+    #[default]
+    TarLz4,
+    /// Independently compressed lz4 blocks with a trailing index; any single file can be read with a seek
+    Indexed,
+}
+
+/// Called with the path and error when a `LazyDB` fails to be recompiled as it's dropped (see `LazyOptions::on_drop_error`)
+pub type DropErrorHook = Arc<dyn Fn(&Path, &LDBError) + Send + Sync>;
+
 pub struct LazyDB {
     path: PathBuf,
-    compressed: bool,
+    /// The compiled file it's written back to when closed (if it was initialised or loaded as one)
+    archive_path: Option<PathBuf>,
+    format: ArchiveFormat,
+    storage: Arc<dyn Storage>,
+    /// Raised whenever anything is modified since it was loaded or compiled
+    dirty: Arc<AtomicBool>,
+    drop_hook: Option<DropErrorHook>,
+    recovery: Recovery,
+    #[cfg(feature = "encryption")]
+    key: Option<encryption::ArchiveKey>,
+    /// Released after the `LazyDB` is dropped (and recompiled)
+    locks: Vec<DbLock>,
 }
 
 impl LazyDB {
+    /// Constructs the default `LazyOptions` for initialising or loading a `LazyDB`
+    #[inline]
+    pub fn options() -> LazyOptions {
+        LazyOptions::new()
+    }
+
     /// Initialises a new LazyDB directory at a specified path.
     /// 
     /// It will create the path if it doesn't already exist and initialise a metadata file with the current version of `lazy-db` if one doesn't exist already.
@@ -65,38 +127,61 @@ impl LazyDB {
     /// **WARNING:** if you initialise the database this way, you cannot compile it in future without errors being thrown!
     /// If you want to compile it, then use `LazyDB::init_db` instead.
     pub fn init(path: impl AsRef<Path>) -> Result<Self, LDBError> {
+        Self::options().init(path)
+    }
+
+    /// Initialises a new `LazyDB` that only exists in memory.
+    ///
+    /// It behaves identically to a `LazyDB` on the filesystem, but nothing is stored on disk unless it is compiled or exported with `LazyDB::export_dir`.
+    pub fn in_memory() -> Result<Self, LDBError> {
+        Self::options().in_memory()
+    }
+
+    /// Initialises a new LazyDB directory at a specified path within a `Storage`.
+    /// 
+    /// It will create the path if it doesn't already exist and initialise a metadata file with the current version of `lazy-db` if one doesn't exist already.
+    /// 
+    /// The `Storage` is used as is; use `LazyOptions::init_in` to wrap it in any enabled layers (like encryption).
+    pub fn init_in(storage: Arc<dyn Storage>, path: impl AsRef<Path>) -> Result<Self, LDBError> {
         let path = path.as_ref();
+        let dirty = Arc::new(AtomicBool::new(true)); // Never compiled yet
+        let storage: Arc<dyn Storage> = Arc::new(DirtyStorage::new(storage, dirty.clone()));
 
         // Check if path exists or not if init it
-        if !path.is_dir() { unwrap_result!((fs::create_dir_all(path)) err => LDBError::IOError(err)) };
+        if !storage.is_dir(path) { unwrap_result!((storage.create_dir_all(path)) err => LDBError::IOError(err)) };
         
-        { // Check if `.meta` file exists if not 
-            let meta = path.join(".meta");
-            if !meta.is_file() {
-                // Write version
-                LazyData::new_binary(
-                    FileWrapper::new_writer(
-                        unwrap_result!((fs::File::create(meta)) err => LDBError::IOError(err))
-                    ), &[VERSION.major, VERSION.minor, VERSION.build],
-                )?;
-            }
-        };
+        // Check if `.meta` container exists if not 
+        if !metadata::exists(storage.as_ref(), path) { metadata::init(storage.as_ref(), path)? };
 
         // Construct Self
         Ok(Self {
             path: path.to_path_buf(),
-            compressed: false,
+            archive_path: None,
+            format: ArchiveFormat::default(),
+            storage,
+            dirty,
+            drop_hook: None,
+            recovery: Recovery::default(),
+            #[cfg(feature = "encryption")]
+            key: None,
+            locks: Vec::new(),
         })
     }
 
     /// Initialise a new compiled `LazyDB` (compressed tarball) at the specified path.
     ///
-    /// It will create the path if it doesn't already exist and initialise a metadata file with the current version of `lazy-db` if one doesn't exist already.
+    /// If the path has no extension, `.ldb` is added. It will create the path if it doesn't already exist and initialise a metadata file with the current version of `lazy-db` if one doesn't exist already.
     pub fn init_db(path: impl AsRef<Path>) -> Result<Self, LDBError> {
-        let dir_path = path.as_ref().with_extension("modb");
-        let mut this = Self::init(dir_path)?;
-        this.compressed = true;
-        Ok(this)
+        Self::options().init_db(path)
+    }
+
+    /// Initialise a new compiled `LazyDB` that is encrypted with a passphrase at the specified path.
+    ///
+    /// The compiled file is encrypted with `XChaCha20-Poly1305` using a key derived from the passphrase with `Argon2`.
+    /// The modifiable directory it is decompiled into while open is **not** encrypted (see `LazyOptions::encrypt_values` for that).
+    #[cfg(feature = "encryption")]
+    pub fn init_db_encrypted(path: impl AsRef<Path>, passphrase: &str) -> Result<Self, LDBError> {
+        Self::options().encrypt_archive(passphrase).init_db(path)
     }
 
     /// Loads a pre-existing LazyDB directory at a specified path.
@@ -105,54 +190,110 @@ impl LazyDB {
     /// 
     /// If the LazyDB is invalid, it will return an error.
     pub fn load_dir(path: impl AsRef<Path>) -> Result<Self, LDBError> {
+        Self::options().load_dir(path)
+    }
+
+    /// Loads a pre-existing LazyDB directory at a specified path within a `Storage`.
+    /// 
+    /// The `Storage` is used as is; use `LazyOptions::load_in` to wrap it in any enabled layers (like encryption).
+    /// 
+    /// If the LazyDB is invalid, it will return an error.
+    pub fn load_in(storage: Arc<dyn Storage>, path: impl AsRef<Path>) -> Result<Self, LDBError> {
+        let this = Self::load_checked(storage, path, Some(&version::VersionPolicy::default()))?;
+        this.escape_legacy_keys()?;
+        Ok(this)
+    }
+
+    /// Loads a pre-existing LazyDB directory within a `Storage`, only checking it's version if there's a policy
+    fn load_checked(storage: Arc<dyn Storage>, path: impl AsRef<Path>, policy: Option<&version::VersionPolicy>) -> Result<Self, LDBError> {
+        let this = Self::load_unrecovered(storage, path, policy)?;
+
+        // Completes or discards any transaction, migration or snapshot that was interrupted
+        transaction::recover(&this.storage, &this.path)?;
+        migration::recover(&this.storage, &this.path)?;
+        snapshot::recover(&this.storage, &this.path)?;
+        Ok(this)
+    }
+
+    /// Loads a pre-existing LazyDB directory within a `Storage` without recovering anything (so nothing is modified)
+    fn load_unrecovered(storage: Arc<dyn Storage>, path: impl AsRef<Path>, policy: Option<&version::VersionPolicy>) -> Result<Self, LDBError> {
         let path = path.as_ref();
+        let dirty = Arc::new(AtomicBool::new(false));
+        let storage: Arc<dyn Storage> = Arc::new(DirtyStorage::new(storage, dirty.clone()));
 
         // Checks if path exists
-        if !path.is_dir() { return Err(LDBError::DirNotFound(path.to_path_buf())) };
+        if !storage.is_dir(path) { return Err(LDBError::DirNotFound(path.to_path_buf())) };
 
-        // Checks if `.meta` file exists or not
-        let meta = path.join(".meta");
-        if !meta.is_file() { return Err(LDBError::FileNotFound(meta)) };
+        // Checks if `.meta` exists or not
+        if !metadata::exists(storage.as_ref(), path) { return Err(LDBError::FileNotFound(path.join(".meta"))) };
 
         // Checks validity of version
-        let read_version = LazyData::load(&meta)?.collect_binary()?;
-        if read_version.len() != 3 { return Err(LDBError::InvalidMetaVersion(meta)) };
-        let read_version = version::Version::new(read_version[0], read_version[1], read_version[2]);
-        if !VERSION.is_compatible(&read_version) { return Err(LDBError::IncompatibleVersion(read_version)) };
+        let read_version = metadata::read_version(storage.as_ref(), path)?;
+        if let Some(policy) = policy {
+            if !policy.allows(&VERSION, &read_version) { return Err(LDBError::IncompatibleVersion(read_version, VERSION, policy.clone())) };
+        }
 
         // Constructs Self
         Ok(Self {
             path: path.to_path_buf(),
-            compressed: false,
+            archive_path: None,
+            format: ArchiveFormat::default(),
+            storage,
+            dirty,
+            drop_hook: None,
+            recovery: Recovery::default(),
+            #[cfg(feature = "encryption")]
+            key: None,
+            locks: Vec::new(),
         })
     }
 
-    /// Loads a pre-existing LazyDB file (compressed tarball) at a specified path
+    /// Loads a pre-existing compiled LazyDB file at a specified path
     /// 
     /// Loads LazyDB as `read-write` allowing for modification of the data within it.
     /// 
-    /// If a directory version of the LazyDatabase exists, it will load the directory version instead of decompiling.
+    /// The format of the compiled file is detected from it's magic bytes and kept when it is recompiled.
+    /// It's recompiled back to exactly the same path (whatever it's extension), replacing it atomically.
+    /// 
+    /// If a directory version of the LazyDatabase was left behind by a crash, it's recovered if it's newer (see `LazyDB::recovery`).
     /// 
     /// If the LazyDB is invalid, it will return an error.
     pub fn load_db(path: impl AsRef<Path>) -> Result<Self, LDBError> {
-        let path = path.as_ref();
-        let mod_path = path.with_extension("modb");
+        Self::options().load_db(path)
+    }
 
-        // Checks if other loaded version exists
-        if mod_path.is_dir() { return Self::load_dir(mod_path) }
+    /// Loads a pre-existing compiled LazyDB file that is encrypted with a passphrase at a specified path
+    /// 
+    /// Loads LazyDB as `read-write` allowing for modification of the data within it; it is encrypted again with the same passphrase when recompiled.
+    /// 
+    /// Returns `LDBError::DecryptionFailed` if the passphrase is wrong or the file has been tampered with.
+    #[cfg(feature = "encryption")]
+    pub fn load_db_encrypted(path: impl AsRef<Path>, passphrase: &str) -> Result<Self, LDBError> {
+        Self::options().encrypt_archive(passphrase).load_db(path)
+    }
 
-        // Decompiles database
-        Self::decompile(path, &mod_path)?;
-        let mut ldb = Self::load_dir(mod_path)?;
-        ldb.compressed = true;
+    /// Opens a pre-existing compiled LazyDB file at a specified path as `read-only`
+    /// 
+    /// Data is read straight out of the compiled file without decompiling it to disk, so any attempts to modify it will return an error.
+    /// Single reads are only cheap with `ArchiveFormat::Indexed`, as `ArchiveFormat::TarLz4` has to be decompressed up to the data being read.
+    /// 
+    /// If the LazyDB is invalid, it will return an error.
+    pub fn open_archive(path: impl AsRef<Path>) -> Result<Self, LDBError> {
+        Self::options().open_archive(path)
+    }
 
-        Ok(ldb)
+    /// Opens a pre-existing compiled LazyDB file or LazyDB directory at a specified path as `read-only`
+    /// 
+    /// Writes are refused at the type level (see `ReadOnlyContainer`), and nothing is ever recompiled, recovered or deleted.
+    /// Compiled files are read without decompiling them (like `LazyDB::open_archive`).
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<ReadOnlyDB, LDBError> {
+        Self::options().open_read_only(path)
     }
 
     /// Gets the 'root' container of the `LazyDB`
     #[inline]
     pub fn as_container(&self) -> Result<LazyContainer, LDBError> {
-        LazyContainer::load(&self.path)
+        LazyContainer::load_in(self.storage.clone(), &self.path)
     }
 
     #[inline]
@@ -160,46 +301,277 @@ impl LazyDB {
         &self.path
     }
 
-    /// Compiles a modifiable `LazyDatabase` directory into a compressed tarball (doesn't delete the modifable directory).
+    /// Reads the `LazyData` addressed by a path (like `/people/Dave::age`)
+    #[inline]
+    pub fn get(&self, path: impl IntoLazyPath) -> Result<LazyData, LDBError> {
+        self.as_container()?.get(path)
+    }
+
+    /// Writes the `LazyData` addressed by a path (like `/people/Dave::age`) with a function like `LazyData::new_u8`
+    /// 
+    /// Any missing containers along the path are created.
+    #[inline]
+    pub fn set(&self, path: impl IntoLazyPath, f: impl FnOnce(LazyWriter) -> Result<(), LDBError>) -> Result<(), LDBError> {
+        self.as_container()?.set(path, f)
+    }
+
+    /// Gets the `LazyContainer` addressed by a path (like `/people/Dave`), creating any containers along it that don't exist yet
+    #[inline]
+    pub fn get_or_create(&self, path: impl IntoLazyPath) -> Result<LazyContainer, LDBError> {
+        self.as_container()?.get_or_create(path)
+    }
+
+    /// Makes sure everything written so far is durably stored (like checkpointing the write-ahead log; see `LazyOptions::write_ahead_log`)
+    #[inline]
+    pub fn checkpoint(&self) -> Result<(), LDBError> {
+        unwrap_result!((self.storage.checkpoint()) err => LDBError::IOError(err));
+        Ok(())
+    }
+
+    /// Removes the `LazyContainer` or `LazyData` addressed by a path (like `/people/Dave`)
+    #[inline]
+    pub fn remove(&self, path: impl IntoLazyPath) -> Result<(), LDBError> {
+        self.as_container()?.remove_path(path)
+    }
+
+    /// Checks if anything has been modified since the `LazyDB` was loaded or last compiled
+    #[inline]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::Acquire)
+    }
+
+    /// Closes the `LazyDB`, reporting any errors instead of ignoring them like `Drop` does
+    /// 
+    /// If it's compiled (like with `LazyDB::load_db`) and anything was modified, it's recompiled and the compiled file is verified;
+    /// only then is the modifiable directory removed. On error the modifiable directory is kept, so nothing is lost.
+    pub fn close(mut self) -> Result<(), LDBError> {
+        let result = self.finish();
+        self.archive_path = None; // Already done (or failed), so `Drop` won't try again
+        result
+    }
+
+    /// Recompiles (if modified), verifies and removes the modifiable directory of a compiled `LazyDB`
+    fn finish(&mut self) -> Result<(), LDBError> {
+        let archive_path = match &self.archive_path {
+            Some(x) => x.clone(),
+            None => return Ok(()), // If not compressed do nothing
+        };
+
+        if self.is_dirty() || !archive_path.is_file() {
+            self.next_generation()?;
+            self.set_compiled()?;
+            self.write_back(&archive_path)?;
+            self.dirty.store(false, Ordering::Release);
+        }
+
+        snapshot::store(&self.path, &archive_path)?;
+        unwrap_result!((fs::remove_dir_all(&self.path)) err => LDBError::IOError(err));
+        self.archive_path = None;
+        Ok(())
+    }
+
+    /// Compiles into a temporary file next to the compiled file, then verifies and syncs it before renaming it over the compiled file
+    fn write_back(&self, archive_path: &Path) -> Result<(), LDBError> {
+        let tmp_path = sidecar(archive_path, TMP_SIDECAR);
+        let result = self.compile(&tmp_path)
+            .map_err(LDBError::IOError)
+            .and_then(|_| self.verify(&tmp_path))
+            .and_then(|_| {
+                unwrap_result!((fs::File::open(&tmp_path).and_then(|x| x.sync_all())) err => LDBError::IOError(err));
+                unwrap_result!((fs::rename(&tmp_path, archive_path)) err => LDBError::IOError(err));
+                Ok(())
+            });
+
+        if result.is_err() { let _ = fs::remove_file(&tmp_path); }
+        result
+    }
+
+    /// Gets the path of the compiled file the `LazyDB` was initialised or loaded as, which it's written back to when closed
+    #[inline]
+    pub fn archive_path(&self) -> Option<&Path> {
+        self.archive_path.as_deref()
+    }
+
+    /// Checks that a compiled file can be read back
+    fn verify(&self, path: &Path) -> Result<(), LDBError> {
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.key { return encryption::verify_file(key, path) };
+
+        let storage = unwrap_result!((ArchiveStorage::open(path, path)) err => LDBError::IOError(err));
+        if !metadata::exists(&storage, path) { return Err(LDBError::FileNotFound(path.join(".meta"))) };
+        Ok(())
+    }
+
+    /// Gets the format the `LazyDB` is compiled into
+    #[inline]
+    pub fn format(&self) -> ArchiveFormat {
+        self.format
+    }
+
+    /// Sets the format the `LazyDB` is compiled into
+    #[inline]
+    pub fn set_format(&mut self, format: ArchiveFormat) {
+        self.format = format;
+    }
+
+    /// Compiles a modifiable `LazyDatabase` directory into it's compiled format (doesn't delete the modifable directory).
     pub fn compile(&self, out_path: impl AsRef<Path>) -> Result<(), std::io::Error> {
+        self.compile_as(out_path, self.format)
+    }
+
+    /// Compiles a modifiable `LazyDatabase` directory into the specified format (doesn't delete the modifable directory).
+    /// 
+    /// If the `LazyDB` is encrypted, the compiled file is encrypted too.
+    pub fn compile_as(&self, out_path: impl AsRef<Path>, format: ArchiveFormat) -> Result<(), std::io::Error> {
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.key {
+            let out_path = out_path.as_ref();
+            let compiled = sidecar(out_path, PLAIN_SIDECAR);
+            Self::compile_plain(&self.storage, &self.path, &compiled, format)?;
+            let result = encryption::encrypt_file(key, &compiled, out_path);
+            fs::remove_file(compiled)?;
+            return result.map_err(|e| match e {
+                LDBError::IOError(e) => e,
+                e => std::io::Error::other(e.to_string()),
+            });
+        }
+
+        Self::compile_plain(&self.storage, &self.path, out_path.as_ref(), format)
+    }
+
+    fn compile_plain(storage: &Arc<dyn Storage>, path: &Path, out_path: &Path, format: ArchiveFormat) -> Result<(), std::io::Error> {
         use lazy_archive::*; // imports
-        let tar = self.path.with_extension("tmp.tar");
+        storage.checkpoint()?;
+        let storage = storage::innermost(storage.as_ref());
+
+        match format {
+            ArchiveFormat::TarLz4 => {
+                let tar = sidecar(out_path, TAR_SIDECAR);
 
-        // Build and compress tarball
-        build_tar(&self.path, &tar)?; // build tar
-        compress_file(&tar, &out_path)?;
+                // Build and compress tarball
+                build_tar(storage, path, &tar)?; // build tar
+                compress_file(&tar, out_path)?;
 
-        // Clean-up
-        fs::remove_file(tar)?;
+                // Clean-up
+                fs::remove_file(tar)?;
+            },
+            ArchiveFormat::Indexed => build_indexed(storage, path, out_path)?,
+        }
 
         Ok(())
     }
 
-    /// Decompiles a compressed tarball `LazyDatabase` into a modifiable directory (doesn't remove the compressed tarball)
-    pub fn decompile(path: impl AsRef<Path>, out_path: impl AsRef<Path>) -> Result<(), LDBError> {
+    /// Exports the `LazyDB` into a plain directory on the filesystem that can be loaded with `LazyDB::load_dir`
+    ///
+    /// Useful for storing an in-memory `LazyDB` on disk.
+    pub fn export_dir(&self, out_path: impl AsRef<Path>) -> Result<(), std::io::Error> {
+        self.storage.checkpoint()?;
+        copy_dir(storage::innermost(self.storage.as_ref()), &self.path, out_path.as_ref())
+    }
+
+    /// Decompiles a compiled `LazyDatabase` into a modifiable directory (doesn't remove the compiled file)
+    /// 
+    /// It's decompiled next to the directory first and then renamed into place, so the directory is never left incomplete.
+    /// The format of the compiled file is detected from it's magic bytes and returned
+    pub fn decompile(path: impl AsRef<Path>, out_path: impl AsRef<Path>) -> Result<ArchiveFormat, LDBError> {
+        let out_path = out_path.as_ref();
+        let tmp_path = sidecar(out_path, TMP_SIDECAR);
+        let marker = tmp_path.join(recovery::DECOMPILING_FILE);
+        let result = fs::create_dir_all(&tmp_path)
+            .and_then(|_| fs::File::create(&marker))
+            .map_err(LDBError::IOError)
+            .and_then(|_| Self::decompile_into(path.as_ref(), &tmp_path))
+            .and_then(|format| {
+                unwrap_result!((fs::remove_file(&marker)) err => LDBError::IOError(err));
+                unwrap_result!((fs::rename(&tmp_path, out_path)) err => LDBError::IOError(err));
+                Ok(format)
+            });
+        if result.is_err() { let _ = fs::remove_dir_all(&tmp_path); }
+        result
+    }
+
+    fn decompile_into(path: &Path, out_path: &Path) -> Result<ArchiveFormat, LDBError> {
         use lazy_archive::*; // imports
-        let path = path.as_ref();
 
         // Checks if the path exists
         if !path.is_file() { return Err(LDBError::FileNotFound(path.to_path_buf())) };
 
-        // Decompress and unpack
-        let tar = path.with_extension("tmp.tar");
-        unwrap_result!((decompress_file(path, &tar)) err => LDBError::IOError(err));
-        unwrap_result!((unpack_tar(&tar, out_path)) err => LDBError::IOError(err));
+        let format = unwrap_result!((detect_format(path)) err => LDBError::IOError(err));
+        match format {
+            ArchiveFormat::TarLz4 => {
+                // Decompress and unpack
+                let tar = sidecar(path, TAR_SIDECAR);
+                unwrap_result!((decompress_file(path, &tar)) err => LDBError::IOError(err));
+                unwrap_result!((unpack_tar(&tar, out_path)) err => LDBError::IOError(err));
 
-        // Clean-up
-        unwrap_result!((fs::remove_file(tar)) err => LDBError::IOError(err));
+                // Clean-up
+                unwrap_result!((fs::remove_file(tar)) err => LDBError::IOError(err));
+            },
+            ArchiveFormat::Indexed => unwrap_result!((unpack_indexed(path, out_path)) err => LDBError::IOError(err)),
+        }
         
-        Ok(())
+        Ok(format)
+    }
+}
+
+/* Sidecar suffixes
+ * Every file or directory kept next to a compiled file is named with one of these (see `sidecar`); temporaries may nest,
+ * as a compile into a `TMP_SIDECAR` (write back) may make a `PLAIN_SIDECAR` (encryption) which may make a `TAR_SIDECAR`.
+ * `recovery::remove_temporaries` removes every combination of them (and nothing else).
+ */
+
+/// Lock file of a compiled file (see `LazyOptions::locking`)
+pub(crate) const LOCK_SIDECAR: &str = "lock";
+/// Modifiable directory a compiled file is decompiled into
+pub(crate) const MODB_SIDECAR: &str = "modb";
+/// Directory a compiled file's snapshots are kept in while it's closed (see `LazyDB::snapshot`)
+pub(crate) const SNAPSHOTS_SIDECAR: &str = "snapshots";
+/// Temporary file (or directory) that's renamed over the path it's named after once complete
+pub(crate) const TMP_SIDECAR: &str = "tmp";
+/// Temporary unencrypted archive, while a compiled file is encrypted or decrypted
+pub(crate) const PLAIN_SIDECAR: &str = "plain.tmp";
+/// Temporary uncompressed tarball, while a compiled file is compressed or decompressed
+pub(crate) const TAR_SIDECAR: &str = "tar.tmp";
+
+/// Gets the path of a file kept next to a compiled file (like it's lock file), named after it's full file name so that it's never shared (`app.db` has `app.db.lock`)
+pub(crate) fn sidecar(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Names of `lazy-db`'s own files and directories within a database's root, which keys can't take
+pub(crate) const RESERVED_NAMES: [&str; 11] = [
+    metadata::META, metadata::METADATA, LOCK_FILE, WAL_FILE, crate::storage::CRYPT_FILE, recovery::GENERATION_FILE,
+    recovery::DECOMPILING_FILE, transaction::TXN_DIR, migration::BACKUP_DIR, migration::BACKUP_TMP_DIR, SNAPSHOTS_DIR,
+];
+
+/// Checks if a name within a database is one of `lazy-db`'s own files (a reserved name, or an item set aside while it's replaced), which containers don't list
+pub(crate) fn is_internal(name: &str) -> bool {
+    RESERVED_NAMES.contains(&name) || (name.starts_with('.') && name.ends_with(REPLACED_SUFFIX))
+}
+
+/// Recursively copies a directory within a `Storage` onto the filesystem
+fn copy_dir(storage: &dyn Storage, path: &Path, out_path: &Path) -> Result<(), std::io::Error> {
+    fs::create_dir_all(out_path)?;
+    for entry in storage.read_dir(path)? {
+        if lazy_archive::is_transient(&entry.name) { continue };
+        let (path, out_path) = (path.join(&entry.name), out_path.join(&entry.name));
+        if entry.is_dir {
+            copy_dir(storage, &path, &out_path)?;
+        } else {
+            std::io::copy(&mut storage.open_read(&path)?, &mut fs::File::create(out_path)?)?;
+        }
     }
+    Ok(())
 }
 
 impl Drop for LazyDB {
     fn drop(&mut self) {
-        if !self.compressed { return }; // If not compressed do nothing
-        let ok = self.compile(self.path.with_extension("ldb")).is_ok();
-        if !ok { return }; // Don't delete if not ok
-        let _ = fs::remove_dir_all(&self.path);
+        // Fallback for when `LazyDB::close` isn't called; the modifiable directory is kept on error
+        if let Err(e) = self.finish() {
+            if let Some(hook) = &self.drop_hook { hook(&self.path, &e) };
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,51 @@
+use super::*;
+use fs2::FileExt;
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+
+/// Name of the lock file in the root of a database directory
+pub const LOCK_FILE: &str = ".lock";
+
+/// An advisory lock (`flock` on unix) on a lock file, held until dropped
+pub(crate) struct DbLock {
+    _file: Option<File>,
+}
+
+impl DbLock {
+    /// Locks a lock file (creating it if needed), waiting up to the timeout for other processes to release it
+    /// 
+    /// Shared locks are skipped if the lock file can't be created (like in a read-only directory or on read-only media).
+    pub(crate) fn acquire(path: &Path, shared: bool, timeout: Duration) -> Result<Self, LDBError> {
+        let file = match OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path) {
+            Ok(x) => x,
+            Err(e) if shared && matches!(e.kind(), ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem) => match File::open(path) {
+                Ok(x) => x,
+                Err(_) => return Ok(Self { _file: None }),
+            },
+            Err(e) => return Err(LDBError::IOError(e)),
+        };
+
+        let try_lock = |file: &File| if shared { FileExt::try_lock_shared(file) } else { FileExt::try_lock_exclusive(file) };
+        if timeout == Duration::MAX {
+            let result = if shared { FileExt::lock_shared(&file) } else { FileExt::lock_exclusive(&file) };
+            unwrap_result!((result) err => LDBError::IOError(err));
+            return Ok(Self { _file: Some(file) });
+        }
+
+        // Polls until the timeout
+        let start = Instant::now();
+        let mut wait = Duration::from_millis(1);
+        loop {
+            match try_lock(&file) {
+                Ok(()) => return Ok(Self { _file: Some(file) }),
+                Err(e) if e.kind() != fs2::lock_contended_error().kind() => return Err(LDBError::IOError(e)),
+                Err(_) if start.elapsed() >= timeout => return Err(LDBError::Locked(path.to_path_buf())),
+                Err(_) => {
+                    std::thread::sleep(wait.min(timeout.saturating_sub(start.elapsed())));
+                    wait = (wait * 2).min(Duration::from_millis(100));
+                },
+            }
+        }
+    }
+}
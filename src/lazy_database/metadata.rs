@@ -0,0 +1,162 @@
+use super::*;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/* Metadata layout
+ * - `.meta`: the version of `lazy-db` the database was written by (binary), which every version of `lazy-db` checks
+ * - `.metadata` container:
+ *   - `schema`: the application's schema version (u64)
+ *   - `created` and `compiled`: seconds since the unix epoch (u64)
+ *   - `escaped_keys`: present once every item is stored under it's escaped key (see `LazyDB::escape_legacy_keys`)
+ *   - `user`: container for the application's own metadata
+ *
+ * `.meta` is kept as the 3 byte file older versions of `lazy-db` read, so they reject newer databases with `IncompatibleVersion`;
+ * databases written by older versions have no `.metadata`, which is created the first time any metadata is written.
+ */
+
+/// Name of the file in a database's root that holds the version of `lazy-db` it was written by
+pub(super) const META: &str = ".meta";
+/// Name of the metadata container in a database's root
+pub(super) const METADATA: &str = ".metadata";
+
+/// Name of the flag in the metadata container that's written once every item is stored under it's escaped key
+const ESCAPED_KEYS: &str = "escaped_keys";
+
+/// The metadata stored in a database's `.meta` file and `.metadata` container (see `LazyDB::metadata`)
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    /// Version of `lazy-db` the database was last written by
+    pub version: version::Version,
+    /// The application's schema version (see `Migrations`)
+    pub schema_version: u64,
+    /// When the database was initialised (`None` if it was initialised by an older version of `lazy-db`)
+    pub created: Option<SystemTime>,
+    /// When the database was last recompiled by `LazyDB::close` (`None` if it never was)
+    pub last_compiled: Option<SystemTime>,
+}
+
+/// Writes the current version of `lazy-db` into a database's `.meta` file
+fn write_version(storage: &dyn Storage, root: &Path) -> Result<(), LDBError> {
+    LazyData::new_binary(
+        LazyWriter::from_boxed(
+            unwrap_result!((storage.open_write(&root.join(META))) err => LDBError::IOError(err))
+        ), &VERSION.to_bytes(),
+    )
+}
+
+/// Writes a time into a metadata container
+fn write_time(storage: &dyn Storage, meta: &Path, key: &str, time: SystemTime) -> Result<(), LDBError> {
+    let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let writer = unwrap_result!((storage.open_write(&meta.join(key))) err => LDBError::IOError(err));
+    LazyData::new_u64(LazyWriter::from_boxed(writer), seconds)
+}
+
+/// Reads a number from a metadata container, if it's there
+fn read_u64(storage: &dyn Storage, meta: &Path, key: &str) -> Result<Option<u64>, LDBError> {
+    let path = meta.join(key);
+    if !storage.is_file(&path) { return Ok(None) };
+    LazyData::load_from(storage, path)?.collect_u64().map(Some)
+}
+
+/// Initialises the metadata of a new database
+pub(super) fn init(storage: &dyn Storage, root: &Path) -> Result<(), LDBError> {
+    write_version(storage, root)?;
+    let metadata = root.join(METADATA);
+    unwrap_result!((storage.create_dir_all(&metadata)) err => LDBError::IOError(err));
+    write_time(storage, &metadata, "created", SystemTime::now())?;
+    let writer = unwrap_result!((storage.open_write(&metadata.join(ESCAPED_KEYS))) err => LDBError::IOError(err));
+    LazyData::new_bool(LazyWriter::from_boxed(writer), true)
+}
+
+/// Checks if every item in a database is stored under it's escaped key (rather than as older versions stored it)
+pub(super) fn keys_escaped(storage: &dyn Storage, root: &Path) -> bool {
+    storage.is_file(&root.join(METADATA).join(ESCAPED_KEYS))
+}
+
+/// Checks if a database has a `.meta` file
+pub(super) fn exists(storage: &dyn Storage, root: &Path) -> bool {
+    storage.is_file(&root.join(META))
+}
+
+/// Reads the version of `lazy-db` a database was last written by
+pub(super) fn read_version(storage: &dyn Storage, root: &Path) -> Result<version::Version, LDBError> {
+    let path = root.join(META);
+    if !storage.is_file(&path) { return Err(LDBError::FileNotFound(path)) };
+
+    // Contents that can't be decoded (like a file that was never fully written) are an invalid version, rather than any other error
+    let read_version = match LazyData::load_from(storage, &path).and_then(|x| x.collect_binary()) {
+        Ok(x) => x,
+        Err(LDBError::IOError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Err(LDBError::InvalidMetaVersion(path)),
+        Err(LDBError::InvalidLazyType(_) | LDBError::IncorrectType(..)) => return Err(LDBError::InvalidMetaVersion(path)),
+        Err(e) => return Err(e),
+    };
+    version::Version::from_bytes(&read_version).ok_or(LDBError::InvalidMetaVersion(path))
+}
+
+/// Checks if an error loading a database means it's `.meta` file is missing or unreadable, so it was never fully initialised
+pub(super) fn is_incomplete(root: &Path, error: &LDBError) -> bool {
+    match error {
+        LDBError::FileNotFound(path) | LDBError::InvalidMetaVersion(path) => *path == root.join(META),
+        _ => false,
+    }
+}
+
+impl LazyDB {
+    /// Reads the metadata stored in the `LazyDB`
+    /// ```rust
+    /// use lazy_db::*;
+    /// let database = LazyDB::in_memory().unwrap();
+    /// let metadata = database.metadata().unwrap();
+    /// assert_eq!(metadata.schema_version, 0);
+    /// assert!(metadata.created.is_some());
+    /// ```
+    pub fn metadata(&self) -> Result<Metadata, LDBError> {
+        let storage = self.storage.as_ref();
+        let metadata = self.path.join(METADATA);
+        let time = |key| Ok::<_, LDBError>(read_u64(storage, &metadata, key)?.map(|x| UNIX_EPOCH + Duration::from_secs(x)));
+
+        Ok(Metadata {
+            version: read_version(storage, &self.path)?,
+            schema_version: read_u64(storage, &metadata, "schema")?.unwrap_or(0),
+            created: time("created")?,
+            last_compiled: time("compiled")?,
+        })
+    }
+
+    /// Gets the container the application can store it's own metadata in (like settings), which is kept apart from it's data
+    /// ```rust
+    /// use lazy_db::*;
+    /// let database = LazyDB::in_memory().unwrap();
+    /// write_container!((database.user_metadata().unwrap()) author = new_string("Dave")).unwrap();
+    /// assert!(database.as_container().unwrap().keys().unwrap().is_empty());
+    /// ```
+    pub fn user_metadata(&self) -> Result<LazyContainer, LDBError> {
+        self.meta_container()?.child_container("user")
+    }
+
+    /// Gets the metadata container, creating it first if the database was written by an older version of `lazy-db`
+    fn meta_container(&self) -> Result<LazyContainer, LDBError> {
+        let metadata = self.path.join(METADATA);
+        if !self.storage.is_dir(&metadata) { unwrap_result!((self.storage.create_dir_all(&metadata)) err => LDBError::IOError(err)) };
+        LazyContainer::load_in(self.storage.clone(), metadata)
+    }
+
+    /// Stores the application's schema version
+    pub(super) fn set_schema_version(&self, version: u64) -> Result<(), LDBError> {
+        write_container!((self.meta_container()?) schema = new_u64(version))
+    }
+
+    /// Stores the current version of `lazy-db`, as the database was upgraded to it
+    pub(super) fn set_version(&self) -> Result<(), LDBError> {
+        write_version(self.storage.as_ref(), &self.path)
+    }
+
+    /// Records that every item is stored under it's escaped key
+    pub(super) fn set_keys_escaped(&self) -> Result<(), LDBError> {
+        write_container!((self.meta_container()?) (ESCAPED_KEYS) = new_bool(true))
+    }
+
+    /// Stores when the `LazyDB` was last compiled
+    pub(super) fn set_compiled(&self) -> Result<(), LDBError> {
+        write_time(self.storage.as_ref(), self.meta_container()?.path(), "compiled", SystemTime::now())
+    }
+}
@@ -0,0 +1,187 @@
+use super::*;
+use std::collections::BTreeMap;
+use crate::lazy_container::{copy_item, legacy_renames};
+
+/// Name of the directory in a database's root that holds a copy of everything while migrations run
+pub(super) const BACKUP_DIR: &str = ".migration";
+/// Name of the directory the backup is copied into before it's complete
+pub(super) const BACKUP_TMP_DIR: &str = ".migration.tmp";
+
+/// A step that upgrades the data in a database's root container to the next schema version
+pub type MigrationStep = Box<dyn Fn(&LazyContainer) -> Result<(), LDBError> + Send + Sync>;
+
+/// A registry of steps that upgrade an application's data from one schema version to the next (see `LazyDB::open_with_migrations`)
+///
+/// Databases start at schema version `0`; the step registered for a version upgrades the data from the version before it.
+/// ```rust
+/// use lazy_db::*;
+/// let mut migrations = Migrations::new();
+/// migrations
+///     .add(1, |root| write_container!((root) name = new_string("Dave")))
+///     .add(2, |root| root.rename("name", "username", Overwrite::Deny));
+///
+/// let database = LazyDB::in_memory().unwrap();
+/// database.migrate(&migrations).unwrap();
+/// assert_eq!(database.schema_version().unwrap(), 2);
+/// assert_eq!(database.get("::username").unwrap().collect_string().unwrap(), "Dave");
+/// ```
+#[derive(Default)]
+pub struct Migrations {
+    steps: BTreeMap<u64, MigrationStep>,
+}
+
+impl Migrations {
+    /// Constructs an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the step that upgrades the data to a schema version (replacing any step already registered for it)
+    pub fn add(&mut self, version: u64, step: impl Fn(&LazyContainer) -> Result<(), LDBError> + Send + Sync + 'static) -> &mut Self {
+        self.steps.insert(version, Box::new(step));
+        self
+    }
+
+    /// Gets the latest schema version a step is registered for (`0` if there are none)
+    pub fn latest(&self) -> u64 {
+        self.steps.keys().next_back().copied().unwrap_or(0)
+    }
+}
+
+impl LazyDB {
+    /// Opens a pre-existing compiled LazyDB file or LazyDB directory at a specified path and upgrades it with migrations
+    ///
+    /// Unlike the other loaders, databases written by any older version of `lazy-db` are opened so the migrations can upgrade them (see `LazyDB::migrate`);
+    /// ones written by newer versions are still checked against the version policy.
+    pub fn open_with_migrations(path: impl AsRef<Path>, migrations: &Migrations) -> Result<Self, LDBError> {
+        Self::options().open_with_migrations(path, migrations)
+    }
+
+    /// Gets the application's schema version stored in the `LazyDB`'s metadata (`0` if it was never migrated)
+    #[inline]
+    pub fn schema_version(&self) -> Result<u64, LDBError> {
+        self.metadata().map(|x| x.schema_version)
+    }
+
+    /// Runs every migration newer than the stored schema version in order, then stores the latest schema version and the current version of `lazy-db` (if it's newer)
+    ///
+    /// Everything is backed up first; if any step fails (or panics) the `LazyDB` is restored from the backup and the error is returned (or the panic resumed).
+    /// If the process crashes part way through, the backup is restored the next time it's loaded.
+    pub fn migrate(&self, migrations: &Migrations) -> Result<(), LDBError> {
+        let schema_version = self.schema_version()?;
+        let stored = metadata::read_version(self.storage.as_ref(), &self.path)?;
+        let outdated = stored < VERSION; // Databases written by newer versions keep their version
+        let mut pending = migrations.steps.range(schema_version + 1..).peekable();
+        if pending.peek().is_none() && !outdated { return Ok(()) };
+
+        self.backup()?;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let container = self.as_container()?;
+            for (version, step) in pending {
+                step(&container)?;
+                self.set_schema_version(*version)?;
+            }
+            if outdated { self.set_version()? };
+            Ok(())
+        }));
+        let result = match result {
+            Ok(x) => x,
+            // Restored before the panic carries on unwinding
+            Err(panic) => {
+                let _ = restore(&self.storage, &self.path);
+                std::panic::resume_unwind(panic);
+            },
+        };
+
+        match result {
+            Ok(()) => self.discard_backup(),
+            Err(e) => {
+                restore(&self.storage, &self.path)?;
+                Err(e)
+            },
+        }
+    }
+
+    /// Renames the items stored by older versions of `lazy-db`, before keys were escaped, to their escaped names (once, as it's recorded in the metadata)
+    ///
+    /// Everything is backed up first, like for a migration, as an item that was renamed twice would have a different key.
+    pub(super) fn escape_legacy_keys(&self) -> Result<(), LDBError> {
+        if metadata::keys_escaped(self.storage.as_ref(), &self.path) { return Ok(()) };
+        let renames = legacy_renames(self.storage.as_ref(), &self.path)?;
+        if renames.is_empty() { return self.set_keys_escaped() };
+
+        self.backup()?;
+        let result = renames.iter()
+            .try_for_each(|(from, to)| self.storage.rename(from, to))
+            .map_err(LDBError::IOError)
+            .and_then(|_| self.set_keys_escaped());
+        match result {
+            Ok(()) => self.discard_backup(),
+            Err(e) => {
+                restore(&self.storage, &self.path)?;
+                Err(e)
+            },
+        }
+    }
+
+    /// Removes the backup once everything it was made for is complete
+    fn discard_backup(&self) -> Result<(), LDBError> {
+        unwrap_result!((self.storage.checkpoint()) err => LDBError::IOError(err));
+        let storage = storage::innermost(self.storage.as_ref());
+        unwrap_result!((storage.remove_dir_all(&self.path.join(BACKUP_DIR))) err => LDBError::IOError(err));
+        Ok(())
+    }
+
+    /// Copies everything in the `LazyDB` into the backup directory, which is only renamed into place once it's complete
+    fn backup(&self) -> Result<(), LDBError> {
+        unwrap_result!((self.storage.checkpoint()) err => LDBError::IOError(err));
+        let storage = storage::innermost(self.storage.as_ref());
+        let (backup, backup_tmp) = (self.path.join(BACKUP_DIR), self.path.join(BACKUP_TMP_DIR));
+        if storage.is_dir(&backup_tmp) { unwrap_result!((storage.remove_dir_all(&backup_tmp)) err => LDBError::IOError(err)) };
+        unwrap_result!((storage.create_dir_all(&backup_tmp)) err => LDBError::IOError(err));
+
+        for name in entries(storage, &self.path)? {
+            unwrap_result!((copy_item(storage, &self.path.join(&name), storage, &backup_tmp.join(&name))) err => LDBError::IOError(err));
+        }
+        unwrap_result!((storage.rename(&backup_tmp, &backup)) err => LDBError::IOError(err));
+        Ok(())
+    }
+}
+
+/// Lists the names of everything in the root of a database that's backed up
+fn entries(storage: &dyn Storage, root: &Path) -> Result<Vec<std::ffi::OsString>, LDBError> {
+    Ok(unwrap_result!((storage.read_dir(root)) err => LDBError::IOError(err))
+        .into_iter()
+        .map(|x| x.name)
+        .filter(|x| !lazy_archive::is_transient(x) && x != BACKUP_DIR && x != BACKUP_TMP_DIR)
+        .collect())
+}
+
+/// Replaces everything in the root of a database with the backup, then removes it
+///
+/// It's copied back rather than moved, so it can be restored again if it's interrupted.
+fn restore(storage: &Arc<dyn Storage>, root: &Path) -> Result<(), LDBError> {
+    unwrap_result!((storage.checkpoint()) err => LDBError::IOError(err));
+    let storage = storage::innermost(storage.as_ref());
+    let backup = root.join(BACKUP_DIR);
+
+    for name in entries(storage, root)? {
+        let path = root.join(name);
+        let result = if storage.is_dir(&path) { storage.remove_dir_all(&path) } else { storage.remove_file(&path) };
+        unwrap_result!((result) err => LDBError::IOError(err));
+    }
+    for name in entries(storage, &backup)? {
+        unwrap_result!((copy_item(storage, &backup.join(&name), storage, &root.join(&name))) err => LDBError::IOError(err));
+    }
+    unwrap_result!((storage.remove_dir_all(&backup)) err => LDBError::IOError(err));
+    Ok(())
+}
+
+/// Restores the backup of a migration that was interrupted, and removes any incomplete backup
+pub(crate) fn recover(storage: &Arc<dyn Storage>, root: &Path) -> Result<(), LDBError> {
+    let inner = storage::innermost(storage.as_ref());
+    let backup_tmp = root.join(BACKUP_TMP_DIR);
+    if inner.is_dir(&backup_tmp) { unwrap_result!((inner.remove_dir_all(&backup_tmp)) err => LDBError::IOError(err)) };
+    if inner.is_dir(&root.join(BACKUP_DIR)) { restore(storage, root)? };
+    Ok(())
+}
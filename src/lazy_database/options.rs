@@ -0,0 +1,372 @@
+use super::*;
+use std::time::Duration;
+
+/// How long opening a database waits for another `LazyDB` to release it's lock by default (see `LazyOptions::lock_timeout`)
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Options for how a `LazyDB` is initialised or loaded
+/// 
+/// All of the `LazyDB` constructors are shorthands for these options with their defaults.
+#[derive(Clone)]
+pub struct LazyOptions {
+    durability: Durability,
+    write_ahead_log: bool,
+    locking: bool,
+    lock_timeout: Duration,
+    drop_hook: Option<DropErrorHook>,
+    version_policy: version::VersionPolicy,
+    /// On while opening with migrations, which upgrade databases written by older versions (whatever the policy)
+    allow_older: bool,
+    #[cfg(feature = "encryption")]
+    archive_passphrase: Option<String>,
+    #[cfg(feature = "encryption")]
+    value_encryption: Option<(String, bool)>,
+}
+
+impl Default for LazyOptions {
+    fn default() -> Self {
+        Self {
+            durability: Durability::default(),
+            write_ahead_log: false,
+            locking: true,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            drop_hook: None,
+            version_policy: version::VersionPolicy::default(),
+            allow_older: false,
+            #[cfg(feature = "encryption")]
+            archive_passphrase: None,
+            #[cfg(feature = "encryption")]
+            value_encryption: None,
+        }
+    }
+}
+
+impl LazyOptions {
+    /// Constructs the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets if databases on the filesystem are locked against other processes while open (on by default)
+    /// 
+    /// Read-write opens take an exclusive lock and read-only opens (`open_archive`) take a shared one.
+    /// This includes other `LazyDB`s in the same process, so a database has to be closed (or dropped) before it's loaded read-write again.
+    /// 
+    /// Directories are locked with a `.lock` file in their root; compiled databases with a lock file next to them, named after the compiled file (`app.ldb.lock`).
+    /// Lock files are empty and left in place when the database is closed (removing them would race with anything waiting on them);
+    /// they can be deleted whenever nothing has the database open.
+    pub fn locking(&mut self, enabled: bool) -> &mut Self {
+        self.locking = enabled;
+        self
+    }
+
+    /// Sets how long to wait for another process to release it's lock before returning `LDBError::Locked`
+    /// 
+    /// Defaults to `DEFAULT_LOCK_TIMEOUT` (so a `LazyDB` that's still being closed can finish); `Duration::ZERO` doesn't wait at all and `Duration::MAX` waits forever.
+    pub fn lock_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Sets the hook that is called if the `LazyDB` fails to be recompiled when it's dropped without `LazyDB::close`
+    /// 
+    /// By default the error is ignored (the modifiable directory is kept, and recovered the next time it's loaded).
+    pub fn on_drop_error(&mut self, hook: impl Fn(&Path, &LDBError) + Send + Sync + 'static) -> &mut Self {
+        self.drop_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Sets which versions of `lazy-db` a database may have been written by for it to be loaded (see `version::VersionPolicy`)
+    /// 
+    /// Defaults to `VersionPolicy::Compatible`; databases written by any other version return `LDBError::IncompatibleVersion`.
+    pub fn version_policy(&mut self, policy: version::VersionPolicy) -> &mut Self {
+        self.version_policy = policy;
+        self
+    }
+
+    /// Locks the lock file at a path if locking is enabled
+    fn lock(&self, path: &Path, shared: bool) -> Result<Option<DbLock>, LDBError> {
+        if !self.locking { return Ok(None) };
+        DbLock::acquire(path, shared, self.lock_timeout).map(Some)
+    }
+
+    /// Sets how far each written `LazyData` is synced to the disk before it replaces the old one (see `storage::Durability`)
+    /// 
+    /// Defaults to `Durability::None`
+    pub fn durability(&mut self, durability: Durability) -> &mut Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Logs every modification to a write-ahead log in the database's root before applying it (see `storage::WalStorage`)
+    /// 
+    /// Only applies to databases on the filesystem. A log left behind by a crash is always replayed when loading, even if this is off.
+    pub fn write_ahead_log(&mut self, enabled: bool) -> &mut Self {
+        self.write_ahead_log = enabled;
+        self
+    }
+
+    /// Encrypts the compiled file with a passphrase (see `LazyDB::init_db_encrypted`)
+    #[cfg(feature = "encryption")]
+    pub fn encrypt_archive(&mut self, passphrase: &str) -> &mut Self {
+        self.archive_passphrase = Some(passphrase.to_string());
+        self
+    }
+
+    /// Encrypts every `LazyData` in the modifiable directory with a passphrase as it is written (see `storage::EncryptedStorage`)
+    /// 
+    /// If `protect_names` is true, the keys of all containers and data are encrypted too.
+    /// This is only decided when the database is initialised; loading ignores it.
+    #[cfg(feature = "encryption")]
+    pub fn encrypt_values(&mut self, passphrase: &str, protect_names: bool) -> &mut Self {
+        self.value_encryption = Some((passphrase.to_string(), protect_names));
+        self
+    }
+
+    /// Wraps the `Storage` of the database at `root` in all of the enabled layers
+    /// 
+    /// If `init` is false, the layers must already have been initialised within the database
+    /// and a database with encrypted values can't be loaded without it's passphrase
+    #[allow(unused_variables)]
+    fn wrap_storage(&self, storage: Arc<dyn Storage>, root: &Path, init: bool) -> Result<Arc<dyn Storage>, LDBError> {
+        let crypt = root.join(crate::storage::CRYPT_FILE);
+
+        #[cfg(feature = "encryption")]
+        if let Some((passphrase, protect_names)) = &self.value_encryption {
+            if !init && !storage.is_file(&crypt) { return Err(LDBError::FileNotFound(crypt)) };
+            return Ok(Arc::new(crate::storage::EncryptedStorage::open(storage, root, passphrase, *protect_names)?));
+        }
+
+        // Encrypted values would otherwise be read as they're stored
+        if storage.is_file(&crypt) { return Err(LDBError::PassphraseRequired(crypt)) };
+        Ok(storage)
+    }
+
+    /// Constructs the `Storage` for a database directory on the filesystem, replaying any write-ahead log within it
+    fn file_storage(&self, path: &Path) -> Result<Arc<dyn Storage>, LDBError> {
+        let storage: Arc<dyn Storage> = Arc::new(FileStorage::new(self.durability));
+        if !self.write_ahead_log && !path.join(WAL_FILE).is_file() { return Ok(storage) };
+
+        let wal = unwrap_result!((WalStorage::open(storage.clone(), path)) err => LDBError::IOError(err));
+        if self.write_ahead_log { Ok(Arc::new(wal)) } else { Ok(storage) }
+    }
+
+    /// Initialises a new LazyDB directory at a specified path (see `LazyDB::init`)
+    pub fn init(&self, path: impl AsRef<Path>) -> Result<LazyDB, LDBError> {
+        let path = path.as_ref();
+        unwrap_result!((fs::create_dir_all(path)) err => LDBError::IOError(err));
+        let lock = self.lock(&path.join(LOCK_FILE), false)?;
+        let mut ldb = self.init_in(self.file_storage(path)?, path)?;
+        ldb.locks.extend(lock);
+        Ok(ldb)
+    }
+
+    /// Initialises a new `LazyDB` that only exists in memory (see `LazyDB::in_memory`)
+    pub fn in_memory(&self) -> Result<LazyDB, LDBError> {
+        self.init_in(Arc::new(MemoryStorage::new()), "/")
+    }
+
+    /// Initialises a new LazyDB directory at a specified path within a `Storage` (see `LazyDB::init_in`)
+    pub fn init_in(&self, storage: Arc<dyn Storage>, path: impl AsRef<Path>) -> Result<LazyDB, LDBError> {
+        let path = path.as_ref();
+
+        // Check if path exists or not if init it
+        if !storage.is_dir(path) { unwrap_result!((storage.create_dir_all(path)) err => LDBError::IOError(err)) };
+
+        let mut ldb = LazyDB::init_in(self.wrap_storage(storage, path, true)?, path)?;
+        ldb.drop_hook = self.drop_hook.clone();
+        Ok(ldb)
+    }
+
+    /// Initialise a new compiled `LazyDB` at the specified path (see `LazyDB::init_db`)
+    pub fn init_db(&self, path: impl AsRef<Path>) -> Result<LazyDB, LDBError> {
+        let path = path.as_ref();
+        let path = if path.extension().is_none() { path.with_extension("ldb") } else { path.to_path_buf() };
+        let lock = self.lock(&sidecar(&path, LOCK_SIDECAR), false)?;
+        let mut this = self.init(sidecar(&path, MODB_SIDECAR))?;
+        this.archive_path = Some(path);
+        this.locks.extend(lock);
+
+        #[cfg(feature = "encryption")]
+        if let Some(passphrase) = &self.archive_passphrase {
+            this.key = Some(encryption::ArchiveKey::new(passphrase)?);
+        }
+
+        Ok(this)
+    }
+
+    /// Loads a pre-existing LazyDB directory at a specified path (see `LazyDB::load_dir`)
+    pub fn load_dir(&self, path: impl AsRef<Path>) -> Result<LazyDB, LDBError> {
+        let path = path.as_ref();
+        if !path.is_dir() { return Err(LDBError::DirNotFound(path.to_path_buf())) };
+        let lock = self.lock(&path.join(LOCK_FILE), false)?;
+        let mut ldb = self.load_in(self.file_storage(path)?, path)?;
+        ldb.locks.extend(lock);
+        Ok(ldb)
+    }
+
+    /// Loads a pre-existing LazyDB directory at a specified path within a `Storage` (see `LazyDB::load_in`)
+    pub fn load_in(&self, storage: Arc<dyn Storage>, path: impl AsRef<Path>) -> Result<LazyDB, LDBError> {
+        let ldb = self.load_unescaped(storage, path)?;
+        ldb.escape_legacy_keys()?;
+        Ok(ldb)
+    }
+
+    /// Loads a pre-existing LazyDB directory within a `Storage`, leaving any items older versions stored under unescaped keys as they are (for archives, which can't be modified)
+    fn load_unescaped(&self, storage: Arc<dyn Storage>, path: impl AsRef<Path>) -> Result<LazyDB, LDBError> {
+        let path = path.as_ref();
+
+        // Checks if path exists
+        if !storage.is_dir(path) { return Err(LDBError::DirNotFound(path.to_path_buf())) };
+
+        let storage = self.wrap_storage(storage, path, false)?;
+        let mut ldb = LazyDB::load_checked(storage, path, Some(&self.checked_policy())).map_err(|e| self.policy_error(e))?;
+        ldb.drop_hook = self.drop_hook.clone();
+        Ok(ldb)
+    }
+
+    /// The version policy databases are checked against when loaded
+    fn checked_policy(&self) -> version::VersionPolicy {
+        if !self.allow_older { return self.version_policy.clone() };
+
+        // Newer versions are still rejected, with the policy that was set
+        let policy = self.version_policy.clone();
+        version::VersionPolicy::custom(move |current, found| found < current || policy.allows(current, found))
+    }
+
+    /// Reports a rejected version with the policy that was set (rather than the one from `checked_policy`)
+    fn policy_error(&self, error: LDBError) -> LDBError {
+        match error {
+            LDBError::IncompatibleVersion(found, current, _) => LDBError::IncompatibleVersion(found, current, self.version_policy.clone()),
+            e => e,
+        }
+    }
+
+    /// Loads a pre-existing compiled LazyDB file at a specified path (see `LazyDB::load_db`)
+    pub fn load_db(&self, path: impl AsRef<Path>) -> Result<LazyDB, LDBError> {
+        let path = path.as_ref();
+
+        // Locked before decompiling, so two processes never share the modifiable directory
+        let lock = self.lock(&sidecar(path, LOCK_SIDECAR), false)?;
+        let mut ldb = self.load_db_locked(path)?;
+        ldb.locks.extend(lock);
+        Ok(ldb)
+    }
+
+    fn load_db_locked(&self, path: &Path) -> Result<LazyDB, LDBError> {
+        let mod_path = sidecar(path, MODB_SIDECAR);
+        let removed = recovery::remove_temporaries(path)?;
+
+        #[cfg(feature = "encryption")]
+        if let Some(passphrase) = &self.archive_passphrase {
+            // Checks if the path exists
+            if !path.is_file() { return Err(LDBError::FileNotFound(path.to_path_buf())) };
+
+            // Decrypts next to it (the passphrase is always verified), then loads it like any other compiled file
+            let decrypted = sidecar(path, PLAIN_SIDECAR);
+            let file = unwrap_result!((fs::File::create(&decrypted)) err => LDBError::IOError(err));
+            let result = encryption::decrypt_file(passphrase, path, file)
+                .and_then(|key| {
+                    let mut ldb = self.load_compiled(&decrypted, path, &mod_path, removed)?;
+                    ldb.archive_path = Some(path.to_path_buf());
+                    ldb.key = Some(key);
+                    Ok(ldb)
+                });
+            let _ = fs::remove_file(&decrypted);
+            return result;
+        }
+
+        self.load_compiled(path, path, &mod_path, removed)
+    }
+
+    /// Loads a compiled file (decrypted from `archive_path`, if it's encrypted), recovering the modifiable directory if one was left behind
+    fn load_compiled(&self, path: &Path, archive_path: &Path, mod_path: &Path, removed: Vec<PathBuf>) -> Result<LazyDB, LDBError> {
+        let mut state = None;
+
+        if mod_path.is_dir() {
+            // Left behind by a process that didn't close it; keeps it unless the compiled file is newer
+            // It's inspected without recovering anything, so nothing in it is modified before it's known to be kept
+            // Any error other than it being incomplete (like a rejected version) leaves it alone, as it may hold unsaved modifications
+            let compiled = recovery::compiled_generation(path);
+            let inspected = LazyDB::load_unrecovered(Arc::new(FileStorage::new(self.durability)), mod_path, Some(&self.checked_policy()))
+                .map_err(|e| self.policy_error(e))
+                .and_then(|x| x.generation());
+            let generation = match inspected {
+                Ok(generation) if compiled.is_none_or(|x| x <= generation) => {
+                    snapshot::retrieve(mod_path, archive_path)?;
+                    let ldb = self.load_dir(mod_path)?;
+                    return self.recovered(ldb, path, RecoveredState::Directory { generation }, removed);
+                },
+                Ok(_) => compiled.unwrap_or_default(),
+                Err(e) if metadata::is_incomplete(mod_path, &e) => compiled.unwrap_or_default(),
+                Err(e) => return Err(e),
+            };
+
+            // Stale (or incomplete), so it's discarded
+            unwrap_result!((fs::remove_dir_all(mod_path)) err => LDBError::IOError(err));
+            state = Some(RecoveredState::Compiled { generation });
+        }
+
+        // Decompiles database
+        let format = LazyDB::decompile(path, mod_path)?;
+        snapshot::retrieve(mod_path, archive_path)?;
+        let mut ldb = self.load_dir(mod_path)?;
+        ldb.archive_path = Some(path.to_path_buf());
+        ldb.format = format;
+        ldb.recovery = Recovery { state, removed };
+
+        Ok(ldb)
+    }
+
+    /// Finishes loading a modifiable directory that was kept, so it's recompiled when closed
+    fn recovered(&self, mut ldb: LazyDB, path: &Path, state: RecoveredState, removed: Vec<PathBuf>) -> Result<LazyDB, LDBError> {
+        ldb.archive_path = Some(path.to_path_buf());
+        if let Ok(format) = lazy_archive::detect_format(path) { ldb.format = format };
+        ldb.dirty.store(true, Ordering::Release); // It may hold modifications that were never compiled
+        ldb.recovery = Recovery { state: Some(state), removed };
+        Ok(ldb)
+    }
+
+    /// Opens a pre-existing compiled LazyDB file at a specified path as `read-only` (see `LazyDB::open_archive`)
+    pub fn open_archive(&self, path: impl AsRef<Path>) -> Result<LazyDB, LDBError> {
+        let path = path.as_ref();
+
+        // Checks if the path exists
+        if !path.is_file() { return Err(LDBError::FileNotFound(path.to_path_buf())) };
+
+        // Indexes the archive with the archive's path as the root
+        let lock = self.lock(&sidecar(path, LOCK_SIDECAR), true)?;
+        let storage = unwrap_result!((ArchiveStorage::open(path, path)) err => LDBError::IOError(err));
+        let format = storage.format();
+        let mut ldb = self.load_unescaped(Arc::new(storage), path)?;
+        ldb.format = format;
+        ldb.locks.extend(lock);
+        Ok(ldb)
+    }
+
+    /// Opens a pre-existing compiled LazyDB file or LazyDB directory at a specified path as `read-only` (see `LazyDB::open_read_only`)
+    /// 
+    /// Locking and value encryption options apply; a shared lock is taken.
+    pub fn open_read_only(&self, path: impl AsRef<Path>) -> Result<ReadOnlyDB, LDBError> {
+        let path = path.as_ref();
+        if path.is_file() { return Ok(ReadOnlyDB::new(self.open_archive(path)?)) };
+        if !path.is_dir() { return Err(LDBError::DirNotFound(path.to_path_buf())) };
+
+        // Any write-ahead log or interrupted transaction is left alone
+        let lock = self.lock(&path.join(LOCK_FILE), true)?;
+        let storage = self.wrap_storage(Arc::new(FileStorage::default()), path, false)?;
+        let mut ldb = LazyDB::load_unrecovered(storage, path, Some(&self.version_policy))?;
+        ldb.locks.extend(lock);
+        Ok(ReadOnlyDB::new(ldb))
+    }
+
+    /// Opens a pre-existing compiled LazyDB file or LazyDB directory at a specified path and upgrades it with migrations (see `LazyDB::open_with_migrations`)
+    pub fn open_with_migrations(&self, path: impl AsRef<Path>, migrations: &Migrations) -> Result<LazyDB, LDBError> {
+        let path = path.as_ref();
+        let mut options = self.clone();
+        options.allow_older = true;
+        let ldb = if path.is_file() { options.load_db(path)? } else { options.load_dir(path)? };
+        ldb.migrate(migrations)?;
+        Ok(ldb)
+    }
+}
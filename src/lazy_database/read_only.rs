@@ -0,0 +1,45 @@
+use super::*;
+
+/// A `LazyDB` opened as `read-only` (see `LazyDB::open_read_only`)
+/// 
+/// It can only be read through `ReadOnlyContainer`s, and is never recompiled, recovered or deleted.
+pub struct ReadOnlyDB {
+    database: LazyDB,
+}
+
+impl ReadOnlyDB {
+    pub(super) fn new(mut database: LazyDB) -> Self {
+        database.archive_path = None; // Never recompiled on drop
+        Self { database }
+    }
+
+    /// Gets the 'root' container of the `LazyDB`
+    #[inline]
+    pub fn as_container(&self) -> Result<ReadOnlyContainer, LDBError> {
+        self.database.as_container().map(ReadOnlyContainer::from)
+    }
+
+    /// Reads the `LazyData` addressed by a path (like `/people/Dave::age`)
+    #[inline]
+    pub fn get(&self, path: impl IntoLazyPath) -> Result<LazyData, LDBError> {
+        self.database.get(path)
+    }
+
+    /// Gets the path of the `LazyDB`
+    #[inline]
+    pub fn path(&self) -> &Path {
+        self.database.path()
+    }
+
+    /// Gets the format the `LazyDB` was compiled into
+    #[inline]
+    pub fn format(&self) -> ArchiveFormat {
+        self.database.format()
+    }
+
+    /// Compiles the `LazyDB` into a new file in it's format (leaves the opened one as it is)
+    #[inline]
+    pub fn compile(&self, out_path: impl AsRef<Path>) -> Result<(), std::io::Error> {
+        self.database.compile(out_path)
+    }
+}
@@ -0,0 +1,105 @@
+use super::*;
+
+/// Name of the file in a database's root that counts how many times it has been compiled by `LazyDB::close`
+///
+/// The compiled file contains the generation it was compiled as, so a modifiable directory left behind by a crash
+/// can be compared against it.
+pub const GENERATION_FILE: &str = ".generation";
+
+/// What was recovered when a compiled `LazyDB` was loaded (see `LazyDB::recovery`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recovery {
+    /// Which state was picked, if a modifiable directory was left behind by a process that didn't close it
+    pub state: Option<RecoveredState>,
+    /// Temporary files left behind by an interrupted compile or decompile that were removed
+    pub removed: Vec<PathBuf>,
+}
+
+impl Recovery {
+    /// Checks if nothing needed to be recovered
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.state.is_none() && self.removed.is_empty()
+    }
+}
+
+/// The state a compiled `LazyDB` was recovered to, after it's modifiable directory was left behind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveredState {
+    /// The modifiable directory was at least as new as the compiled file (or the compiled file was unreadable), so it was kept
+    Directory { generation: u64 },
+    /// The compiled file was newer (or the modifiable directory was incomplete), so the stale directory was discarded
+    Compiled { generation: u64 },
+}
+
+/// Name of the file that marks a directory as being decompiled into, until it's complete
+pub(super) const DECOMPILING_FILE: &str = ".decompiling";
+
+/// Removes the temporary files an interrupted compile, write back or decompile of a compiled file may have left behind
+///
+/// Only files named after the compiled file that could only have been written by `lazy-db` (from their magic bytes),
+/// and directories marked as being decompiled into, are removed.
+pub(super) fn remove_temporaries(path: &Path) -> Result<Vec<PathBuf>, LDBError> {
+    let write_back = sidecar(path, TMP_SIDECAR);
+    let files = archive_temporaries(path).into_iter()
+        .chain(archive_temporaries(&write_back))
+        .chain([write_back]);
+    let decompiling = sidecar(&sidecar(path, MODB_SIDECAR), TMP_SIDECAR);
+
+    let mut removed = Vec::new();
+    if decompiling.join(DECOMPILING_FILE).is_file() {
+        unwrap_result!((fs::remove_dir_all(&decompiling)) err => LDBError::IOError(err));
+        removed.push(decompiling);
+    }
+    for file in files {
+        if !file.is_file() || !lazy_archive::is_own_file(&file) { continue };
+        unwrap_result!((fs::remove_file(&file)) err => LDBError::IOError(err));
+        removed.push(file);
+    }
+    Ok(removed)
+}
+
+/// Gets the temporary files that compiling, decompiling, encrypting or decrypting an archive at a path may leave next to it
+fn archive_temporaries(path: &Path) -> [PathBuf; 3] {
+    let plain = sidecar(path, PLAIN_SIDECAR);
+    [sidecar(path, TAR_SIDECAR), sidecar(&plain, TAR_SIDECAR), plain]
+}
+
+/// Reads the generation of the database at `root` within a `Storage` (databases that were never closed are generation `0`)
+fn read_generation(storage: &dyn Storage, root: &Path) -> Result<u64, LDBError> {
+    let path = root.join(GENERATION_FILE);
+    if !storage.is_file(&path) { return Ok(0) };
+    LazyData::load_from(storage, path)?.collect_u64()
+}
+
+/// Reads the generation a compiled file was compiled as; `None` if it's missing or unreadable
+pub(super) fn compiled_generation(path: &Path) -> Option<u64> {
+    let storage = ArchiveStorage::open(path, path).ok()?;
+    if !metadata::exists(&storage, path) { return None };
+    read_generation(&storage, path).ok()
+}
+
+impl LazyDB {
+    /// Gets what was recovered when the `LazyDB` was loaded, like a modifiable directory left behind by a crash
+    /// ```rust
+    /// use lazy_db::*;
+    /// let database = LazyDB::in_memory().unwrap();
+    /// assert!(database.recovery().is_empty());
+    /// ```
+    #[inline]
+    pub fn recovery(&self) -> &Recovery {
+        &self.recovery
+    }
+
+    /// Gets the generation of the `LazyDB`, which is incremented every time it's recompiled by `LazyDB::close`
+    pub fn generation(&self) -> Result<u64, LDBError> {
+        read_generation(self.storage.as_ref(), &self.path)
+    }
+
+    /// Increments the generation of the `LazyDB`, before it's recompiled
+    pub(super) fn next_generation(&self) -> Result<(), LDBError> {
+        let generation = self.generation()? + 1;
+        let writer = unwrap_result!((self.storage.open_write(&self.path.join(GENERATION_FILE))) err => LDBError::IOError(err));
+        LazyData::new_u64(LazyWriter::from_boxed(writer), generation)
+    }
+}
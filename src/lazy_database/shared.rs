@@ -0,0 +1,128 @@
+use super::*;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{RwLock, RwLockWriteGuard};
+
+/// Amount of locks the paths of a `SharedLazyDB` are spread across
+const STRIPES: usize = 64;
+
+/// A thread-safe handle to a `LazyDB` that can be cloned and shared between threads
+///
+/// Every path is guarded by one of a fixed set of reader/writer locks (picked by the path's hash), so
+/// readers never see a value while it's being written and writers to different keys don't block each other.
+/// Removing a container, or making a transaction, locks everything.
+///
+/// The `LazyDB` is dropped (and recompiled, if compiled) once the last handle is dropped.
+/// ```rust
+/// use lazy_db::*;
+/// let database = LazyDB::in_memory().unwrap().shared();
+/// let handle = database.clone();
+/// std::thread::spawn(move || handle.set("::count", |file| LazyData::new_u8(file, 1)).unwrap()).join().unwrap();
+/// assert_eq!(database.read("::count", |data| data.collect_u8()).unwrap(), 1);
+/// ```
+#[derive(Clone)]
+pub struct SharedLazyDB {
+    inner: Arc<Shared>,
+}
+
+struct Shared {
+    database: LazyDB,
+    stripes: Box<[RwLock<()>]>,
+}
+
+impl SharedLazyDB {
+    /// Wraps a `LazyDB` in a thread-safe handle
+    pub fn new(database: LazyDB) -> Self {
+        Self {
+            inner: Arc::new(Shared {
+                database,
+                stripes: (0..STRIPES).map(|_| RwLock::new(())).collect(),
+            }),
+        }
+    }
+
+    /// Gets the lock that guards a path
+    fn stripe(&self, path: &LazyPath) -> &RwLock<()> {
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        &self.inner.stripes[hasher.finish() as usize % STRIPES]
+    }
+
+    /// Write-locks every path (always in the same order, so it can't deadlock)
+    fn lock_all(&self) -> Vec<RwLockWriteGuard<'_, ()>> {
+        self.inner.stripes.iter().map(|x| x.write().unwrap_or_else(|e| e.into_inner())).collect()
+    }
+
+    /// Reads the `LazyData` addressed by a path (like `/people/Dave::age`) with a function like `LazyData::collect_u8`
+    ///
+    /// The path can't be written to until the function returns.
+    pub fn read<T>(&self, path: impl IntoLazyPath, f: impl FnOnce(LazyData) -> Result<T, LDBError>) -> Result<T, LDBError> {
+        let path = path.into_lazy_path()?;
+        let _guard = self.stripe(&path).read().unwrap_or_else(|e| e.into_inner());
+        f(self.inner.database.get(&path)?)
+    }
+
+    /// Writes the `LazyData` addressed by a path (like `/people/Dave::age`) with a function like `LazyData::new_u8`
+    ///
+    /// Any missing containers along the path are created.
+    pub fn set(&self, path: impl IntoLazyPath, f: impl FnOnce(LazyWriter) -> Result<(), LDBError>) -> Result<(), LDBError> {
+        let path = path.into_lazy_path()?;
+        let _guard = self.stripe(&path).write().unwrap_or_else(|e| e.into_inner());
+        self.inner.database.set(&path, f)
+    }
+
+    /// Reads the current `LazyData` addressed by a path (if it exists) and writes a new one, without any other writes to the path in between
+    /// ```rust
+    /// use lazy_db::*;
+    /// let database = LazyDB::in_memory().unwrap().shared();
+    /// database.update("::count", |old, file| {
+    ///     let count = match old { Some(x) => x.collect_u32()?, None => 0 };
+    ///     LazyData::new_u32(file, count + 1)
+    /// }).unwrap();
+    /// ```
+    pub fn update(&self, path: impl IntoLazyPath, f: impl FnOnce(Option<LazyData>, LazyWriter) -> Result<(), LDBError>) -> Result<(), LDBError> {
+        let path = path.into_lazy_path()?;
+        let _guard = self.stripe(&path).write().unwrap_or_else(|e| e.into_inner());
+        let old = match self.inner.database.get(&path) {
+            Ok(x) => Some(x),
+            Err(LDBError::FileNotFound(_)) | Err(LDBError::DirNotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        self.inner.database.set(&path, |file| f(old, file))
+    }
+
+    /// Removes the `LazyContainer` or `LazyData` addressed by a path (like `/people/Dave`)
+    pub fn remove(&self, path: impl IntoLazyPath) -> Result<(), LDBError> {
+        let path = path.into_lazy_path()?;
+        if path.is_data() {
+            let _guard = self.stripe(&path).write().unwrap_or_else(|e| e.into_inner());
+            return self.inner.database.remove(&path);
+        }
+
+        // Everything within the container is removed too
+        let _guards = self.lock_all();
+        self.inner.database.remove(&path)
+    }
+
+    /// Applies a set of changes all together or not at all (see `LazyDB::transaction`); nothing else can be read or written until it's done
+    pub fn transaction<T>(&self, f: impl FnOnce(&mut Transaction) -> Result<T, LDBError>) -> Result<T, LDBError> {
+        let _guards = self.lock_all();
+        self.inner.database.transaction(f)
+    }
+
+    /// Gets the `LazyDB` the handle shares
+    ///
+    /// Anything done through it directly bypasses the handle's locks.
+    #[inline]
+    pub fn database(&self) -> &LazyDB {
+        &self.inner.database
+    }
+}
+
+impl LazyDB {
+    /// Wraps the `LazyDB` in a thread-safe handle (see `SharedLazyDB`)
+    #[inline]
+    pub fn shared(self) -> SharedLazyDB {
+        SharedLazyDB::new(self)
+    }
+}
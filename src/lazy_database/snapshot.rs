@@ -0,0 +1,178 @@
+use super::*;
+use std::ffi::OsStr;
+
+/// Name of the directory in a database's root that holds it's snapshots
+pub(crate) const SNAPSHOTS_DIR: &str = ".snapshots";
+/// Name of the directory (within the snapshots directory) a snapshot is linked into before it's complete
+const STAGING_DIR: &str = ".staging";
+/// Name of the file (within the snapshots directory) that holds the name of the snapshot being restored, until it's restored
+const RESTORING_FILE: &str = ".restoring";
+
+/// Checks if an item in a database's root is part of it's snapshots (everything but internal files that are specific to the live database)
+fn is_snapshotted(name: &OsStr) -> bool {
+    !lazy_archive::is_transient(name)
+        && ![GENERATION_FILE, TXN_DIR, BACKUP_DIR, BACKUP_TMP_DIR].iter().any(|x| name == *x)
+}
+
+/// Recursively links a file or directory to a new path (see `Storage::link`)
+fn link_tree(storage: &dyn Storage, from: &Path, to: &Path) -> Result<(), LDBError> {
+    if !storage.is_dir(from) {
+        unwrap_result!((storage.link(from, to)) err => LDBError::IOError(err));
+        return Ok(());
+    }
+
+    unwrap_result!((storage.create_dir_all(to)) err => LDBError::IOError(err));
+    for entry in unwrap_result!((storage.read_dir(from)) err => LDBError::IOError(err)) {
+        link_tree(storage, &from.join(&entry.name), &to.join(&entry.name))?;
+    }
+    Ok(())
+}
+
+/// Removes a file or directory
+fn remove(storage: &dyn Storage, path: &Path) -> Result<(), LDBError> {
+    let result = if storage.is_dir(path) { storage.remove_dir_all(path) } else { storage.remove_file(path) };
+    unwrap_result!((result) err => LDBError::IOError(err));
+    Ok(())
+}
+
+/// Replaces everything in a database's root (that's part of it's snapshots) with a snapshot
+///
+/// The name of the snapshot is stored first and only removed once it's done, so an interrupted restore is finished when the database is next loaded.
+fn restore(storage: &dyn Storage, root: &Path, name: &str) -> Result<(), LDBError> {
+    let snapshots = root.join(SNAPSHOTS_DIR);
+    let snapshot = snapshots.join(name);
+    let restoring = snapshots.join(RESTORING_FILE);
+    unwrap_result!((storage.checkpoint()) err => LDBError::IOError(err));
+    LazyData::new_string(LazyWriter::from_boxed(unwrap_result!((storage.open_write(&restoring)) err => LDBError::IOError(err))), name)?;
+
+    for entry in unwrap_result!((storage.read_dir(root)) err => LDBError::IOError(err)) {
+        if is_snapshotted(&entry.name) { remove(storage, &root.join(&entry.name))? };
+    }
+    for entry in unwrap_result!((storage.read_dir(&snapshot)) err => LDBError::IOError(err)) {
+        link_tree(storage, &snapshot.join(&entry.name), &root.join(&entry.name))?;
+    }
+
+    unwrap_result!((storage.remove_file(&restoring)) err => LDBError::IOError(err));
+    Ok(())
+}
+
+/// Moves the snapshots of a compiled database's modifiable directory next to the compiled file (replacing any there), before the directory is removed
+pub(super) fn store(root: &Path, archive_path: &Path) -> Result<(), LDBError> {
+    let (snapshots, stored) = (root.join(SNAPSHOTS_DIR), sidecar(archive_path, SNAPSHOTS_SIDECAR));
+    if !snapshots.is_dir() { return Ok(()) };
+    if stored.is_dir() { unwrap_result!((fs::remove_dir_all(&stored)) err => LDBError::IOError(err)) };
+    unwrap_result!((fs::rename(&snapshots, &stored)) err => LDBError::IOError(err));
+    Ok(())
+}
+
+/// Moves the snapshots stored next to a compiled file back into it's modifiable directory (unless it still has it's own)
+pub(super) fn retrieve(root: &Path, archive_path: &Path) -> Result<(), LDBError> {
+    let (snapshots, stored) = (root.join(SNAPSHOTS_DIR), sidecar(archive_path, SNAPSHOTS_SIDECAR));
+    if !stored.is_dir() || snapshots.exists() { return Ok(()) };
+    unwrap_result!((fs::rename(&stored, &snapshots)) err => LDBError::IOError(err));
+    Ok(())
+}
+
+/// Finishes restoring a snapshot if it was interrupted, and removes any incomplete snapshot
+pub(crate) fn recover(storage: &Arc<dyn Storage>, root: &Path) -> Result<(), LDBError> {
+    let snapshots = root.join(SNAPSHOTS_DIR);
+    if !storage.is_dir(&snapshots) { return Ok(()) };
+
+    let staging = snapshots.join(STAGING_DIR);
+    if storage.is_dir(&staging) { unwrap_result!((storage.remove_dir_all(&staging)) err => LDBError::IOError(err)) };
+
+    let restoring = snapshots.join(RESTORING_FILE);
+    if storage.is_file(&restoring) {
+        let name = LazyData::load_from(storage.as_ref(), &restoring)?.collect_string()?;
+        restore(storage.as_ref(), root, &name)?;
+    }
+    Ok(())
+}
+
+impl LazyDB {
+    /// Takes a read-only, point-in-time snapshot of everything in the `LazyDB` under a name (see `LazyDB::open_snapshot` and `LazyDB::restore_snapshot`)
+    ///
+    /// Snapshots are cheap: every file is hard linked (where the filesystem supports it) rather than copied, and as writes always replace files
+    /// instead of modifying them, anything written afterwards is copied-on-write and never changes the snapshot.
+    /// Snapshots are left out when the `LazyDB` is compiled; a compiled `LazyDB` keeps them in a directory next to the compiled file
+    /// while it's closed (`app.ldb.snapshots`), so they're only available once it's loaded read-write again.
+    /// Compiled files encrypted with a passphrase can't be snapshotted, as the snapshots would be stored unencrypted next to them.
+    /// ```rust
+    /// use lazy_db::*;
+    /// let database = LazyDB::in_memory().unwrap();
+    /// database.set("::count", |file| LazyData::new_u8(file, 1)).unwrap();
+    /// database.snapshot("before-import").unwrap();
+    /// database.set("::count", |file| LazyData::new_u8(file, 2)).unwrap();
+    ///
+    /// assert_eq!(database.open_snapshot("before-import").unwrap().get("::count").unwrap().collect_u8().unwrap(), 1);
+    /// database.restore_snapshot("before-import").unwrap();
+    /// assert_eq!(database.get("::count").unwrap().collect_u8().unwrap(), 1);
+    /// ```
+    pub fn snapshot(&self, name: &str) -> Result<(), LDBError> {
+        #[cfg(feature = "encryption")]
+        if self.key.is_some() {
+            return Err(LDBError::IOError(std::io::Error::new(std::io::ErrorKind::Unsupported, "Encrypted compiled databases can't be snapshotted")));
+        }
+
+        let snapshots = self.path.join(SNAPSHOTS_DIR);
+        let snapshot = snapshots.join(escape_key(name)?);
+        if self.storage.is_dir(&snapshot) { return Err(LDBError::AlreadyExists(snapshot)) };
+
+        // Linked into a staging directory that's only renamed into place once it's complete
+        let staging = snapshots.join(STAGING_DIR);
+        let storage = self.storage.as_ref();
+        unwrap_result!((storage.checkpoint()) err => LDBError::IOError(err));
+        if storage.is_dir(&staging) { unwrap_result!((storage.remove_dir_all(&staging)) err => LDBError::IOError(err)) };
+        unwrap_result!((storage.create_dir_all(&staging)) err => LDBError::IOError(err));
+        for entry in unwrap_result!((storage.read_dir(&self.path)) err => LDBError::IOError(err)) {
+            if is_snapshotted(&entry.name) { link_tree(storage, &self.path.join(&entry.name), &staging.join(&entry.name))? };
+        }
+        unwrap_result!((storage.rename(&staging, &snapshot)) err => LDBError::IOError(err));
+        Ok(())
+    }
+
+    /// Lists the names of all of the snapshots of the `LazyDB` (sorted)
+    pub fn list_snapshots(&self) -> Result<Vec<String>, LDBError> {
+        let snapshots = self.path.join(SNAPSHOTS_DIR);
+        if !self.storage.is_dir(&snapshots) { return Ok(Vec::new()) };
+
+        let mut names: Vec<String> = unwrap_result!((self.storage.read_dir(&snapshots)) err => LDBError::IOError(err))
+            .into_iter()
+            .filter(|x| x.is_dir && !x.name.to_string_lossy().starts_with('.'))
+            .map(|x| unescape_key(&x.name.to_string_lossy()))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Gets the path of a snapshot, if it exists
+    fn snapshot_path(&self, name: &str) -> Result<PathBuf, LDBError> {
+        let snapshot = self.path.join(SNAPSHOTS_DIR).join(escape_key(name)?);
+        if !self.storage.is_dir(&snapshot) { return Err(LDBError::DirNotFound(snapshot)) };
+        Ok(snapshot)
+    }
+
+    /// Opens a snapshot of the `LazyDB` as `read-only`
+    pub fn open_snapshot(&self, name: &str) -> Result<ReadOnlyDB, LDBError> {
+        let snapshot = self.snapshot_path(name)?;
+        let mut ldb = LazyDB::load_unrecovered(self.storage.clone(), snapshot, None)?;
+        ldb.format = self.format;
+        Ok(ReadOnlyDB::new(ldb))
+    }
+
+    /// Rolls everything in the `LazyDB` back to a snapshot (the snapshot itself is kept)
+    ///
+    /// If it's interrupted, the restore is finished the next time the `LazyDB` is loaded.
+    pub fn restore_snapshot(&self, name: &str) -> Result<(), LDBError> {
+        let snapshot = self.snapshot_path(name)?;
+        let name = snapshot.file_name().unwrap().to_string_lossy().into_owned();
+        restore(self.storage.as_ref(), &self.path, &name)
+    }
+
+    /// Removes a snapshot of the `LazyDB`
+    pub fn remove_snapshot(&self, name: &str) -> Result<(), LDBError> {
+        let snapshot = self.snapshot_path(name)?;
+        unwrap_result!((self.storage.remove_dir_all(&snapshot)) err => LDBError::IOError(err));
+        Ok(())
+    }
+}
@@ -0,0 +1,283 @@
+use super::*;
+use std::io::Read;
+
+/// The shadow directory (within the database's root) that transactions are staged in
+pub(super) const TXN_DIR: &str = ".txn";
+/// The file within `TXN_DIR` whose existence marks the transaction as committed; it holds the list of operations to apply
+const COMMIT_FILE: &str = "commit";
+
+/// A staged change to the database
+///
+/// Each operation owns the item `TXN_DIR/{index}` (and `TXN_DIR/{index}.old`), which are used to tell if it has been applied yet.
+enum Operation {
+    /// Renames the staged `LazyData` to the path
+    Write(LazyPath),
+    /// Renames the item at the path into the shadow directory (or marks it as removed if it doesn't exist)
+    Remove(LazyPath),
+    /// Renames the staged `LazyContainer` to the path, after renaming the old one into the shadow directory
+    Replace(LazyPath),
+}
+
+impl Operation {
+    fn path(&self) -> &LazyPath {
+        match self {
+            Operation::Write(x) | Operation::Remove(x) | Operation::Replace(x) => x,
+        }
+    }
+
+    fn tag(&self) -> u8 {
+        match self {
+            Operation::Write(_) => 0,
+            Operation::Remove(_) => 1,
+            Operation::Replace(_) => 2,
+        }
+    }
+}
+
+/// A set of changes to a `LazyDB` that are applied all together or not at all (see `LazyDB::transaction`)
+///
+/// All changes are staged in a shadow directory until the transaction is committed.
+/// Reads through the transaction see the staged changes.
+pub struct Transaction<'a> {
+    database: &'a LazyDB,
+    staging: PathBuf,
+    operations: Vec<Operation>,
+    committed: bool,
+}
+
+impl<'a> Transaction<'a> {
+    fn new(database: &'a LazyDB) -> Result<Self, LDBError> {
+        let staging = database.path.join(TXN_DIR);
+        if database.storage.is_dir(&staging) { return Err(LDBError::AlreadyExists(staging)) };
+        unwrap_result!((database.storage.create_dir_all(&staging)) err => LDBError::IOError(err));
+        Ok(Self {
+            database,
+            staging,
+            operations: Vec::new(),
+            committed: false,
+        })
+    }
+
+    /// Generates the path within the shadow directory for the next operation
+    fn next_staged(&self) -> PathBuf {
+        self.staging.join(self.operations.len().to_string())
+    }
+
+    /// Stages writing the `LazyData` at a path (like `/people/Dave::age`) with a function like `LazyData::new_u8`
+    ///
+    /// Any missing containers along the path are created when the transaction is committed.
+    pub fn set(&mut self, path: impl IntoLazyPath, f: impl FnOnce(LazyWriter) -> Result<(), LDBError>) -> Result<(), LDBError> {
+        let path = path.into_lazy_path()?;
+        if !path.is_data() { return Err(LDBError::InvalidPath(path.to_string(), String::from("expected a data path"))) };
+        let staged = self.next_staged();
+        f(LazyWriter::from_boxed(unwrap_result!((self.database.storage.open_write(&staged)) err => LDBError::IOError(err))))?;
+        self.operations.push(Operation::Write(path));
+        Ok(())
+    }
+
+    /// Stages removing the `LazyContainer` or `LazyData` at a path
+    pub fn remove(&mut self, path: impl IntoLazyPath) -> Result<(), LDBError> {
+        let path = path.into_lazy_path()?;
+        if path.is_root() { return Err(LDBError::InvalidPath(path.to_string(), String::from("cannot remove the root container"))) };
+        self.operations.push(Operation::Remove(path));
+        Ok(())
+    }
+
+    /// Stages a new, empty `LazyContainer` that **replaces** the one at a path when the transaction is committed
+    ///
+    /// Everything written into the returned container is part of the transaction.
+    pub fn new_container(&mut self, path: impl IntoLazyPath) -> Result<LazyContainer, LDBError> {
+        let path = path.into_lazy_path()?;
+        if path.is_data() || path.is_root() { return Err(LDBError::InvalidPath(path.to_string(), String::from("expected a non-root container path"))) };
+        let container = unwrap_result!((LazyContainer::init_in(self.database.storage.clone(), self.next_staged())) err => LDBError::IOError(err));
+        self.operations.push(Operation::Replace(path));
+        Ok(container)
+    }
+
+    /// Stages a copy of the `LazyContainer` at a path (as seen by the transaction) that replaces it when the transaction is committed
+    ///
+    /// Everything written into the returned container (like with `LazyObject::store_lazy`) is part of the transaction.
+    pub fn edit_container(&mut self, path: impl IntoLazyPath) -> Result<LazyContainer, LDBError> {
+        let path = path.into_lazy_path()?;
+        let original = self.get_container(&path)?;
+        let container = self.new_container(&path)?;
+        for key in original.keys()? {
+            original.copy_tree(&key, &container, &key, Overwrite::Replace)?;
+        }
+        Ok(container)
+    }
+
+    /// Reads the `LazyData` at a path, including any staged changes
+    pub fn get(&self, path: impl IntoLazyPath) -> Result<LazyData, LDBError> {
+        let path = path.into_lazy_path()?;
+        match self.resolve(&path)? {
+            Some((container, relative)) => container.get(relative),
+            None => self.database.get(path),
+        }
+    }
+
+    /// Reads the `LazyContainer` at a path, including any staged changes
+    ///
+    /// Changes to containers that aren't staged with `new_container` or `edit_container` **aren't** part of the transaction.
+    pub fn get_container(&self, path: impl IntoLazyPath) -> Result<LazyContainer, LDBError> {
+        let path = path.into_lazy_path()?;
+        match self.resolve(&path)? {
+            Some((container, relative)) => container.get_container(relative),
+            None => self.database.as_container()?.get_container(path),
+        }
+    }
+
+    /// Finds the latest staged operation that affects a path; returns the staged container to read it from (relative to it) if there is one
+    fn resolve(&self, path: &LazyPath) -> Result<Option<(LazyContainer, LazyPath)>, LDBError> {
+        let not_found = || LDBError::FileNotFound(PathBuf::from(path.to_string()));
+        for (i, operation) in self.operations.iter().enumerate().rev() {
+            let staged = self.staging.join(i.to_string());
+            match operation {
+                Operation::Write(x) if x == path => {
+                    return Ok(Some((LazyContainer::load_in(self.database.storage.clone(), &self.staging)?, LazyPath::root().join_data(i.to_string()))));
+                },
+                Operation::Remove(x) if x == path || path.strip_prefix(x).is_some() => return Err(not_found()),
+                Operation::Replace(x) => if let Some(relative) = path.strip_prefix(x) {
+                    return Ok(Some((LazyContainer::load_in(self.database.storage.clone(), staged)?, relative)));
+                },
+                _ => (),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Writes the commit marker and applies all of the staged operations
+    fn commit(mut self) -> Result<(), LDBError> {
+        let storage = &self.database.storage;
+
+        // Once the marker exists, the transaction will be completed even after a crash
+        let mut manifest = Vec::new();
+        for operation in self.operations.iter() {
+            let path = operation.path().to_string();
+            manifest.push(operation.tag());
+            manifest.extend_from_slice(&(path.len() as u32).to_le_bytes());
+            manifest.extend_from_slice(path.as_bytes());
+        }
+        let mut writer = LazyWriter::from_boxed(unwrap_result!((storage.open_write(&self.staging.join(COMMIT_FILE))) err => LDBError::IOError(err)));
+        writer.write(&manifest)?;
+        writer.finish()?;
+        self.committed = true;
+
+        apply(storage, &self.database.path, &self.operations)
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        // Rolls back (also when unwinding from a panic)
+        if !self.committed { let _ = self.database.storage.remove_dir_all(&self.staging); }
+    }
+}
+
+/// Converts a `LazyPath` into the full path it is stored at within a database
+fn full_path(root: &Path, path: &LazyPath) -> Result<PathBuf, LDBError> {
+    let mut full = root.to_path_buf();
+    for key in path.keys() { full.push(escape_key(key)?) };
+    Ok(full)
+}
+
+/// Moves the file or directory at `path` (if any) out of the way to `trash`; returns if there was anything to move
+fn move_out(storage: &Arc<dyn Storage>, path: &Path, trash: &Path) -> std::io::Result<bool> {
+    if !storage.is_file(path) && !storage.is_dir(path) { return Ok(false) };
+    storage.rename(path, trash)?;
+    Ok(true)
+}
+
+/// Applies the operations of a committed transaction, skipping any that were already applied, then removes the shadow directory
+fn apply(storage: &Arc<dyn Storage>, root: &Path, operations: &[Operation]) -> Result<(), LDBError> {
+    let staging = root.join(TXN_DIR);
+    let root_container = LazyContainer::load_in(storage.clone(), root)?;
+
+    for (i, operation) in operations.iter().enumerate() {
+        let staged = staging.join(i.to_string());
+        let old = staging.join(format!("{i}.old"));
+        let target = full_path(root, operation.path())?;
+        let result = match operation {
+            Operation::Write(_) | Operation::Replace(_) => {
+                if !storage.is_file(&staged) && !storage.is_dir(&staged) { continue }; // Already applied
+                root_container.get_or_create(operation.path().parent().unwrap_or_default())?;
+
+                // Files are replaced by the rename itself, anything else is moved out of the way first
+                let replaceable = matches!(operation, Operation::Write(_)) && storage.is_file(&target);
+                let moved = if replaceable { Ok(false) } else { move_out(storage, &target, &old) };
+                moved.and_then(|_| storage.rename(&staged, &target))
+            },
+            Operation::Remove(_) => {
+                if storage.is_file(&staged) || storage.is_dir(&staged) { continue }; // Already applied
+                match move_out(storage, &target, &staged) {
+                    Ok(true) => Ok(()),
+                    Ok(false) => storage.open_write(&staged).and_then(|x| x.commit()), // Marks it as applied
+                    Err(e) => Err(e),
+                }
+            },
+        };
+        unwrap_result!((result) err => LDBError::IOError(err));
+    }
+
+    // The marker goes first, so an interrupted clean-up is discarded rather than re-applied
+    unwrap_result!((storage.remove_file(&staging.join(COMMIT_FILE))) err => LDBError::IOError(err));
+    unwrap_result!((storage.remove_dir_all(&staging)) err => LDBError::IOError(err));
+    Ok(())
+}
+
+/// Completes a transaction that was interrupted after committing, or discards one that was interrupted before; returns if there was one
+pub(crate) fn recover(storage: &Arc<dyn Storage>, root: &Path) -> Result<bool, LDBError> {
+    let staging = root.join(TXN_DIR);
+    if !storage.is_dir(&staging) { return Ok(false) };
+
+    let commit = staging.join(COMMIT_FILE);
+    if !storage.is_file(&commit) {
+        unwrap_result!((storage.remove_dir_all(&staging)) err => LDBError::IOError(err));
+        return Ok(true);
+    }
+
+    // Parses the list of operations
+    let mut manifest = Vec::new();
+    unwrap_result!((storage.open_read(&commit).and_then(|mut x| x.read_to_end(&mut manifest))) err => LDBError::IOError(err));
+    let mut operations = Vec::new();
+    let mut bytes = manifest.as_slice();
+    while !bytes.is_empty() {
+        let invalid = || LDBError::InvalidPath(commit.to_string_lossy().to_string(), String::from("corrupt transaction manifest"));
+        if bytes.len() < 5 { return Err(invalid()) };
+        let length = u32::from_le_bytes(bytes[1..5].try_into().unwrap()) as usize;
+        let path = bytes.get(5..5 + length).ok_or_else(invalid)?;
+        let path = LazyPath::parse(std::str::from_utf8(path).map_err(|_| invalid())?)?;
+        operations.push(match bytes[0] {
+            0 => Operation::Write(path),
+            1 => Operation::Remove(path),
+            2 => Operation::Replace(path),
+            _ => return Err(invalid()),
+        });
+        bytes = &bytes[5 + length..];
+    }
+
+    apply(storage, root, &operations)?;
+    Ok(true)
+}
+
+impl LazyDB {
+    /// Applies a set of changes all together or not at all
+    ///
+    /// Changes are staged through the `Transaction` and only applied once the closure returns `Ok`;
+    /// if it returns an `Err` (or panics) they are discarded. A commit interrupted by a crash is completed the next time the database is loaded.
+    /// ```rust
+    /// use lazy_db::*;
+    /// let database = LazyDB::in_memory().unwrap();
+    /// database.transaction(|tx| {
+    ///     tx.set("/people/Dave::age", |file| LazyData::new_u8(file, 21))?;
+    ///     tx.set("/people/Dave::fav_colour", |file| LazyData::new_string(file, "Blue"))?;
+    ///     tx.remove("/people/Bob")
+    /// }).unwrap();
+    /// ```
+    pub fn transaction<T>(&self, f: impl FnOnce(&mut Transaction) -> Result<T, LDBError>) -> Result<T, LDBError> {
+        let mut tx = Transaction::new(self)?;
+        let result = f(&mut tx)?;
+        tx.commit()?;
+        Ok(result)
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::{LDBError, validate_key};
+use crate::lazy_container::{percent_encode, percent_decode};
+
+/// An address of a `LazyContainer` or `LazyData` within a tree of containers
+/// 
+/// Written like the paths in the database macros: containers are separated by `/` and data is separated with `::` (`/people/Dave::age`).
+/// A path without `::` addresses a container, `/` is the root container and `::version` is data within the root container.
+/// 
+/// Within the written form, `%`, `/` and `:` in keys are percent-encoded (`%25`, `%2F` and `%3A`).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LazyPath {
+    containers: Vec<String>,
+    data: Option<String>,
+}
+
+impl LazyPath {
+    /// Constructs the path of the root container
+    #[inline]
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// Constructs a path from the keys of containers and optionally the key of data within the last container
+    pub fn new<S: Into<String>>(containers: impl IntoIterator<Item = S>, data: Option<impl Into<String>>) -> Result<Self, LDBError> {
+        let path = Self {
+            containers: containers.into_iter().map(Into::into).collect(),
+            data: data.map(Into::into),
+        };
+        for key in path.keys() { validate_key(key)? };
+        Ok(path)
+    }
+
+    /// Parses a path like `/people/Dave::age`
+    pub fn parse(path: &str) -> Result<Self, LDBError> {
+        let invalid = |reason: &str| LDBError::InvalidPath(path.to_string(), reason.to_string());
+
+        // Split off the data
+        let (containers, data) = match path.split_once("::") {
+            Some((containers, data)) => (containers, Some(data)),
+            None => (path, None),
+        };
+        if data.map(|x| x.contains("::")).unwrap_or(false) { return Err(invalid("paths can only contain one '::'")) };
+
+        // Split the containers
+        let containers = containers.strip_prefix('/').unwrap_or(containers);
+        let containers: Vec<String> = if containers.is_empty() { Vec::new() }
+            else { containers.split('/').map(percent_decode).collect::<Option<_>>().ok_or_else(|| invalid("invalid escape sequence"))? };
+        let data = match data {
+            Some(x) => Some(percent_decode(x).ok_or_else(|| invalid("invalid escape sequence"))?),
+            None => None,
+        };
+
+        Self::new(containers, data)
+    }
+
+    /// The keys of the containers leading up to the addressed item
+    #[inline]
+    pub fn containers(&self) -> &[String] {
+        &self.containers
+    }
+
+    /// The key of the addressed `LazyData`; `None` if a container is addressed
+    #[inline]
+    pub fn data(&self) -> Option<&str> {
+        self.data.as_deref()
+    }
+
+    /// The key of the addressed item; `None` for the root container
+    pub fn key(&self) -> Option<&str> {
+        self.data.as_deref().or(self.containers.last().map(String::as_str))
+    }
+
+    /// All of the keys within the path in order
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.containers.iter().map(String::as_str).chain(self.data.as_deref())
+    }
+
+    /// Checks if the path addresses a `LazyData`
+    #[inline]
+    pub fn is_data(&self) -> bool {
+        self.data.is_some()
+    }
+
+    /// Checks if the path addresses the root container
+    #[inline]
+    pub fn is_root(&self) -> bool {
+        self.containers.is_empty() && self.data.is_none()
+    }
+
+    /// The amount of containers and data the path goes through (the root has a depth of 0)
+    pub fn depth(&self) -> usize {
+        self.containers.len() + self.data.is_some() as usize
+    }
+
+    /// The path of the container that holds the addressed item; `None` for the root container
+    pub fn parent(&self) -> Option<Self> {
+        let mut parent = self.clone();
+        if parent.data.take().is_none() { parent.containers.pop()?; };
+        Some(parent)
+    }
+
+    /// Returns the path relative to a container path that it is nested within (or equal to); `None` if it isn't within it
+    pub fn strip_prefix(&self, prefix: &LazyPath) -> Option<Self> {
+        if prefix.is_data() { return None };
+        let containers = self.containers.strip_prefix(prefix.containers.as_slice())?;
+        Some(Self {
+            containers: containers.to_vec(),
+            data: self.data.clone(),
+        })
+    }
+
+    /// Constructs the path of a container nested within the container this path addresses
+    pub fn join_container(&self, key: impl Into<String>) -> Self {
+        let mut containers = self.containers.clone();
+        containers.push(key.into());
+        Self { containers, data: None }
+    }
+
+    /// Constructs the path of `LazyData` within the container this path addresses
+    pub fn join_data(&self, key: impl Into<String>) -> Self {
+        Self { containers: self.containers.clone(), data: Some(key.into()) }
+    }
+}
+
+fn escape_segment(key: &str) -> String {
+    percent_encode(key, &['/', ':'])
+}
+
+impl fmt::Display for LazyPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_root() { return write!(f, "/") };
+        for container in self.containers.iter() {
+            write!(f, "/{}", escape_segment(container))?;
+        }
+        if let Some(data) = &self.data {
+            write!(f, "::{}", escape_segment(data))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for LazyPath {
+    type Err = LDBError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Anything that can be converted into a `LazyPath` (parsing strings)
+pub trait IntoLazyPath {
+    fn into_lazy_path(self) -> Result<LazyPath, LDBError>;
+}
+
+impl IntoLazyPath for LazyPath {
+    fn into_lazy_path(self) -> Result<LazyPath, LDBError> {
+        Ok(self)
+    }
+}
+
+impl IntoLazyPath for &LazyPath {
+    fn into_lazy_path(self) -> Result<LazyPath, LDBError> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoLazyPath for &str {
+    fn into_lazy_path(self) -> Result<LazyPath, LDBError> {
+        LazyPath::parse(self)
+    }
+}
+
+impl IntoLazyPath for String {
+    fn into_lazy_path(self) -> Result<LazyPath, LDBError> {
+        LazyPath::parse(&self)
+    }
+}
+
+impl IntoLazyPath for &String {
+    fn into_lazy_path(self) -> Result<LazyPath, LDBError> {
+        LazyPath::parse(self)
+    }
+}
@@ -1,5 +1,4 @@
 mod converter;
-pub use converter::*;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u8)]
@@ -11,6 +11,17 @@
 //!     - A collection of `LazyData`, think of it like an object from `OOP` or a directory in a file system
 //!     - An abstaction of the underlying filesystem directory
 //! 
+//! ## Compatibility
+//! Every database stores the version of `lazy-db` it was written by in a 3 byte `.meta` file, which every version of `lazy-db` checks before opening it
+//! (see `LazyOptions::version_policy`), so older versions reject newer databases with `LDBError::IncompatibleVersion` rather than misreading them.
+//! The rest of it's metadata (see `LazyDB::metadata`) is kept in a separate `.metadata` container, which older versions ignore.
+//! 
+//! Databases on the filesystem are locked while open (see `LazyOptions::locking`), so loading one read-write that's already open,
+//! even within the same process, waits up to `DEFAULT_LOCK_TIMEOUT` and then returns `LDBError::Locked` where older versions opened it again.
+//! 
+//! Keys are stored with `%`, `/`, `\` and a leading `.` percent-encoded (see `escape_key`). Items stored by older versions under a key containing
+//! any of them are renamed to their escaped keys (once) the first time the database is loaded read-write; until then, read-only opens can't find them by their key.
+//! 
 //! ## Examples
 //! ### Some basic usage
 //! Here is a really basic `LazyDB` that holds some information about a hypothetical person named *'Dave'*
@@ -90,7 +101,11 @@ pub mod version;
 pub mod lazy_database;
 pub mod lazy_container;
 pub mod lazy_trait;
+pub mod lazy_path;
+pub mod storage;
 mod lazy_archive;
+#[cfg(feature = "encryption")]
+mod encryption;
 
 // Prelude
 pub use crate::{
@@ -100,9 +115,15 @@ pub use crate::{
     lazy_database::*,
     lazy_container::*,
     lazy_trait::*,
+    lazy_path::*,
 };
 
-pub const VERSION: version::Version = version::Version::new(1, 2, 1);
+/// The version of `lazy-db`, which is stored in every database it writes (the package version)
+pub const VERSION: version::Version = version::Version::new(
+    version::parse_const(env!("CARGO_PKG_VERSION_MAJOR")),
+    version::parse_const(env!("CARGO_PKG_VERSION_MINOR")),
+    version::parse_const(env!("CARGO_PKG_VERSION_PATCH")),
+);
 
 #[macro_export]
 macro_rules! const_eval {
@@ -0,0 +1,119 @@
+mod file_storage;
+mod memory_storage;
+mod archive_storage;
+mod wal_storage;
+mod dirty_storage;
+#[cfg(feature = "encryption")]
+mod encrypted_storage;
+
+pub use file_storage::*;
+pub use memory_storage::*;
+pub use archive_storage::*;
+pub use wal_storage::*;
+pub(crate) use dirty_storage::*;
+#[cfg(feature = "encryption")]
+pub use encrypted_storage::*;
+
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Name of the file in an encrypted database's root that holds the salt and passphrase check (see `EncryptedStorage`)
+pub const CRYPT_FILE: &str = ".crypt";
+
+/// A backend that `LazyContainer`s and `LazyData` are stored within
+///
+/// All paths passed to a `Storage` are full paths (the container's path joined with the key)
+pub trait Storage: Send + Sync {
+    /// Checks if there is a file at the path
+    fn is_file(&self, path: &Path) -> bool;
+    /// Checks if there is a directory at the path
+    fn is_dir(&self, path: &Path) -> bool;
+    /// Creates a directory and all of it's missing parents
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    /// Removes a file
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+    /// Removes a directory and all of it's contents
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+    /// Lists the direct children of a directory (in no particular order)
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<StorageEntry>>;
+    /// Opens a file for reading
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>>;
+    /// Moves a file or directory (and all of it's contents) to a new path, replacing the file or empty directory there
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    /// Creates (or truncates) a file for writing; the contents are only guaranteed to be stored once the writer is committed
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn StorageWriter>>;
+
+    /// Gets the size of a file's contents in bytes
+    ///
+    /// Defaults to reading through the file.
+    fn file_len(&self, path: &Path) -> io::Result<u64> {
+        io::copy(&mut self.open_read(path)?, &mut io::sink())
+    }
+
+    /// Makes a file at a new path with the same contents as a file, sharing them rather than copying them if possible (like a hard link)
+    ///
+    /// Writes always replace a file rather than modifying it in place, so writing to either path never affects the other.
+    /// Defaults to copying the contents.
+    fn link(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut reader = self.open_read(from)?;
+        let mut writer = self.open_write(to)?;
+        io::copy(&mut reader, &mut writer)?;
+        writer.commit()
+    }
+
+    /// The `Storage` that this one transforms the files of (like `EncryptedStorage`), if any
+    ///
+    /// Compiling a `LazyDB` reads from the innermost `Storage`, so the compiled files are kept as they are stored.
+    fn backing(&self) -> Option<&dyn Storage> {
+        None
+    }
+
+    /// Makes sure everything written so far is durably stored (like `WalStorage` syncing and truncating it's log)
+    /// 
+    /// Defaults to checkpointing the backing `Storage`, if any.
+    fn checkpoint(&self) -> io::Result<()> {
+        match self.backing() {
+            Some(x) => x.checkpoint(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Follows `Storage::backing` to the innermost `Storage`
+pub fn innermost(storage: &dyn Storage) -> &dyn Storage {
+    let mut storage = storage;
+    while let Some(x) = storage.backing() { storage = x };
+    storage
+}
+
+/// A writer created by a `Storage` that must be committed to store what was written
+pub trait StorageWriter: Write + Send {
+    /// Stores everything that was written (consumes writer)
+    fn commit(self: Box<Self>) -> io::Result<()>;
+}
+
+impl StorageWriter for std::fs::File {
+    fn commit(self: Box<Self>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// How far a committed `StorageWriter` makes sure the file has reached the disk before replacing the old one
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Durability {
+    /// Leaves it up to the OS (the replacement is still atomic, but may be lost if the system crashes)
+    #[default]
+    None,
+    /// Syncs the file's data to the disk
+    Flush,
+    /// Syncs the file's data and metadata, and then the directory it was renamed within
+    Fsync,
+}
+
+/// A direct child of a directory within a `Storage`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageEntry {
+    pub name: OsString,
+    pub is_dir: bool,
+}
@@ -0,0 +1,146 @@
+use super::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+use lz4_flex::frame::FrameDecoder;
+use crate::lazy_archive;
+use crate::ArchiveFormat;
+
+enum ArchiveNode {
+    /// Offset and length of the file's data within the archive, followed by it's decompressed size
+    File(u64, u64, u64),
+    Dir,
+}
+
+/// A read-only `Storage` that serves files straight out of a compiled `LazyDB`
+///
+/// An index of all the entries is built when opened, so no data is ever decompressed to disk.
+/// With `ArchiveFormat::TarLz4` each read decompresses the archive up to the file it is reading,
+/// while with `ArchiveFormat::Indexed` each read seeks straight to the file's own compressed block.
+pub struct ArchiveStorage {
+    archive: PathBuf,
+    format: ArchiveFormat,
+    index: HashMap<PathBuf, ArchiveNode>,
+}
+
+impl ArchiveStorage {
+    /// Opens a compiled `LazyDB` and indexes it's entries under the `root` path
+    pub fn open(archive: impl AsRef<Path>, root: impl AsRef<Path>) -> io::Result<Self> {
+        let (archive, root) = (archive.as_ref(), root.as_ref());
+        let format = lazy_archive::detect_format(archive)?;
+        let mut index = HashMap::new();
+        index.insert(root.to_path_buf(), ArchiveNode::Dir);
+
+        let mut insert = |path: PathBuf, node: ArchiveNode| {
+            // Parent directories are implied by the entry's path
+            for parent in path.ancestors().skip(1) {
+                if !parent.starts_with(root) || index.contains_key(parent) { break };
+                index.insert(parent.to_path_buf(), ArchiveNode::Dir);
+            }
+            index.insert(path, node);
+        };
+
+        match format {
+            ArchiveFormat::TarLz4 => {
+                let mut tar = tar::Archive::new(FrameDecoder::new(File::open(archive)?));
+                for entry in tar.entries()? {
+                    let entry = entry?;
+                    let node = if entry.header().entry_type().is_dir() { ArchiveNode::Dir }
+                        else { ArchiveNode::File(entry.raw_file_position(), entry.size(), entry.size()) };
+                    insert(root.join(entry.path()?), node);
+                }
+            },
+            ArchiveFormat::Indexed => {
+                for entry in lazy_archive::read_index(&mut File::open(archive)?)? {
+                    let node = if entry.is_dir { ArchiveNode::Dir }
+                        else { ArchiveNode::File(entry.offset, entry.length, entry.size) };
+                    insert(root.join(entry.path), node);
+                }
+            },
+        }
+
+        Ok(Self {
+            archive: archive.to_path_buf(),
+            format,
+            index,
+        })
+    }
+
+    /// Returns the format of the archive
+    #[inline]
+    pub fn format(&self) -> ArchiveFormat {
+        self.format
+    }
+}
+
+fn read_only(path: &Path) -> Error {
+    Error::new(ErrorKind::PermissionDenied, format!("Cannot modify '{}' within a read-only archive", path.to_string_lossy()))
+}
+
+impl Storage for ArchiveStorage {
+    fn is_file(&self, path: &Path) -> bool {
+        matches!(self.index.get(path), Some(ArchiveNode::File(..)))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        matches!(self.index.get(path), Some(ArchiveNode::Dir))
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        if self.is_dir(path) { Ok(()) } else { Err(read_only(path)) }
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        Err(read_only(path))
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        Err(read_only(path))
+    }
+
+    fn rename(&self, from: &Path, _: &Path) -> io::Result<()> {
+        Err(read_only(from))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<StorageEntry>> {
+        if !self.is_dir(path) { return Err(Error::new(ErrorKind::NotFound, format!("'{}' not found", path.to_string_lossy()))) };
+        Ok(self.index.iter()
+            .filter(|(x, _)| x.parent() == Some(path))
+            .filter_map(|(x, node)| Some(StorageEntry {
+                name: x.file_name()?.to_os_string(),
+                is_dir: matches!(node, ArchiveNode::Dir),
+            })).collect())
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        let (offset, length, size) = match self.index.get(path) {
+            Some(ArchiveNode::File(offset, length, size)) => (*offset, *length, *size),
+            _ => return Err(Error::new(ErrorKind::NotFound, format!("'{}' not found", path.to_string_lossy()))),
+        };
+
+        match self.format {
+            ArchiveFormat::TarLz4 => {
+                // Decompress up to the start of the file's data
+                let mut decoder = FrameDecoder::new(File::open(&self.archive)?);
+                io::copy(&mut (&mut decoder).take(offset), &mut io::sink())?;
+                Ok(Box::new(decoder.take(length)))
+            },
+            ArchiveFormat::Indexed => {
+                let block = lazy_archive::read_indexed_block(&mut File::open(&self.archive)?, offset, length, size)?;
+                Ok(Box::new(io::Cursor::new(block)))
+            },
+        }
+    }
+
+    fn file_len(&self, path: &Path) -> io::Result<u64> {
+        match self.index.get(path) {
+            Some(ArchiveNode::File(_, _, size)) => Ok(*size),
+            _ => Err(Error::new(ErrorKind::NotFound, format!("'{}' not found", path.to_string_lossy()))),
+        }
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn StorageWriter>> {
+        Err(read_only(path))
+    }
+}
@@ -0,0 +1,76 @@
+use super::*;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A `Storage` that raises a flag whenever anything within an inner `Storage` is modified
+pub(crate) struct DirtyStorage {
+    inner: Arc<dyn Storage>,
+    dirty: Arc<AtomicBool>,
+}
+
+impl DirtyStorage {
+    pub fn new(inner: Arc<dyn Storage>, dirty: Arc<AtomicBool>) -> Self {
+        Self { inner, dirty }
+    }
+
+    fn mark(&self) {
+        self.dirty.store(true, Ordering::Release);
+    }
+}
+
+impl Storage for DirtyStorage {
+    fn is_file(&self, path: &Path) -> bool {
+        self.inner.is_file(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.inner.is_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        if self.inner.is_dir(path) { return Ok(()) };
+        self.mark();
+        self.inner.create_dir_all(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.mark();
+        self.inner.remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.mark();
+        self.inner.remove_dir_all(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<StorageEntry>> {
+        self.inner.read_dir(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.mark();
+        self.inner.rename(from, to)
+    }
+
+    fn link(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.mark();
+        self.inner.link(from, to)
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        self.inner.open_read(path)
+    }
+
+    fn file_len(&self, path: &Path) -> io::Result<u64> {
+        self.inner.file_len(path)
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn StorageWriter>> {
+        self.mark();
+        self.inner.open_write(path)
+    }
+
+    fn backing(&self) -> Option<&dyn Storage> {
+        Some(self.inner.as_ref())
+    }
+}
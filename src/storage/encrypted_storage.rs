@@ -0,0 +1,304 @@
+use super::*;
+use crate::{LDBError, encryption};
+use std::ffi::OsStr;
+use std::io::{Cursor, Error, ErrorKind};
+use std::os::unix::prelude::{OsStrExt, OsStringExt};
+use std::path::{Component, PathBuf};
+use std::sync::Arc;
+use chacha20poly1305::{XChaCha20Poly1305, XNonce, KeyInit};
+use chacha20poly1305::aead::{Aead, OsRng, AeadCore, Payload};
+use chacha20::XChaCha20;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use blake2::Blake2sMac256;
+use blake2::digest::Mac;
+
+/* `.crypt` file format
+ * - `CRYPT_MAGIC`
+ * - argon2 salt (16 bytes)
+ * - protect names flag (u8)
+ * - nonce (24 bytes) and encrypted `CRYPT_CHECK` to verify the passphrase
+ *
+ * Encrypted file format
+ * - nonce (24 bytes) followed by the XChaCha20-Poly1305 ciphertext
+ * - the associated data is the file's path relative to the database's root (`/` separated), so a file can't be swapped for another
+ *   (within `.snapshots/<name>`, the path relative to the snapshot)
+ *
+ * Protected name format (lowercase hex)
+ * - keyed blake2s hash of the name (first 24 bytes) used as the nonce, followed by the XChaCha20 encrypted name
+ */
+
+const CRYPT_MAGIC: &[u8; 8] = b"LDBVALUE";
+const CRYPT_CHECK: &[u8] = b"lazy-db";
+const NONCE_SIZE: usize = 24;
+
+/// A `Storage` that transparently encrypts the contents of every file written to an inner `Storage`
+/// (and optionally the names of all containers and data) with `XChaCha20-Poly1305`
+///
+/// The key is derived from a passphrase with `Argon2`; the salt lives in a `.crypt` file in the database's root.
+/// Files and directories with names starting with `.` are internal to `lazy-db` and are neither encrypted nor renamed.
+///
+/// Every file is bound to it's path, so moving a file (or directory) re-encrypts it: it's copied and then removed rather than atomically renamed.
+///
+/// Protected names are encoded as hex with a 24 byte prefix, so each key must be under ~100 bytes to fit within filesystem limits.
+pub struct EncryptedStorage {
+    inner: Arc<dyn Storage>,
+    root: PathBuf,
+    cipher: XChaCha20Poly1305,
+    name_keys: Option<([u8; 32], [u8; 32])>,
+}
+
+impl EncryptedStorage {
+    /// Opens the encryption of a database's root within an inner `Storage`; if the root has no `.crypt` file yet, a new one is created
+    /// 
+    /// Whether names are protected is decided when the `.crypt` file is created and is ignored afterwards.
+    /// 
+    /// Returns `LDBError::DecryptionFailed` if the passphrase is wrong.
+    pub fn open(inner: Arc<dyn Storage>, root: impl AsRef<Path>, passphrase: &str, protect_names: bool) -> Result<Self, LDBError> {
+        let root = root.as_ref().to_path_buf();
+        let crypt = root.join(CRYPT_FILE);
+        let io_err = LDBError::IOError;
+
+        // Reads or creates the `.crypt` file
+        let (salt, protect_names, check) = if inner.is_file(&crypt) {
+            let mut bytes = Vec::new();
+            inner.open_read(&crypt).and_then(|mut x| x.read_to_end(&mut bytes)).map_err(io_err)?;
+            let header = CRYPT_MAGIC.len() + encryption::SALT_SIZE + 1;
+            if bytes.len() < header || &bytes[..CRYPT_MAGIC.len()] != CRYPT_MAGIC { return Err(LDBError::DecryptionFailed(crypt)) };
+            let salt = bytes[CRYPT_MAGIC.len()..header - 1].to_vec();
+            (salt, bytes[header - 1] != 0, Some(bytes[header..].to_vec()))
+        } else { (encryption::new_salt().to_vec(), protect_names, None) };
+
+        // Derives the value key and both name keys
+        let keys: [u8; 96] = encryption::derive_key(passphrase, &salt)?;
+        let this = Self {
+            inner,
+            cipher: XChaCha20Poly1305::new(keys[..32].into()),
+            name_keys: if protect_names { Some((keys[32..64].try_into().unwrap(), keys[64..].try_into().unwrap())) } else { None },
+            root,
+        };
+
+        match check {
+            // Verifies the passphrase
+            Some(check) => if this.decrypt(&check, &[]).as_deref() != Some(CRYPT_CHECK) { return Err(LDBError::DecryptionFailed(crypt)) },
+            None => {
+                let mut bytes = CRYPT_MAGIC.to_vec();
+                bytes.extend_from_slice(&salt);
+                bytes.push(protect_names as u8);
+                bytes.extend_from_slice(&Self::encrypt(&this.cipher, CRYPT_CHECK, &[]).map_err(io_err)?);
+                let mut writer = this.inner.open_write(&crypt).map_err(io_err)?;
+                writer.write_all(&bytes).map_err(io_err)?;
+                writer.commit().map_err(io_err)?;
+            },
+        }
+
+        Ok(this)
+    }
+
+    fn encrypt(cipher: &XChaCha20Poly1305, bytes: &[u8], aad: &[u8]) -> io::Result<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher.encrypt(&nonce, Payload { msg: bytes, aad })
+            .map_err(|_| Error::other("Failed to encrypt"))?;
+        let mut out = nonce.to_vec();
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    fn decrypt(&self, bytes: &[u8], aad: &[u8]) -> Option<Vec<u8>> {
+        if bytes.len() < NONCE_SIZE { return None };
+        let (nonce, ciphertext) = bytes.split_at(NONCE_SIZE);
+        self.cipher.decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad }).ok()
+    }
+
+    /// Gets the data a file at the path is bound to: it's path relative to the root
+    ///
+    /// Snapshots are copies of the root, so a file within `.snapshots/<name>` is bound to the same path as it's live counterpart (and can be linked).
+    fn binding(&self, path: &Path) -> Vec<u8> {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let relative = relative.strip_prefix(crate::lazy_database::SNAPSHOTS_DIR)
+            .map(|x| x.components().skip(1).collect::<PathBuf>())
+            .unwrap_or_else(|_| relative.to_path_buf());
+        let components: Vec<&[u8]> = relative.components()
+            .filter_map(|x| match x {
+                Component::Normal(x) => Some(x.as_bytes()),
+                _ => None,
+            }).collect();
+        components.join(&b'/')
+    }
+
+    /// Checks if moving a file (or everything within a directory) to a new path can keep it's stored contents as they are
+    fn keeps_contents(&self, from: &Path, to: &Path) -> bool {
+        if self.is_dir(from) { return self.binding(from) == self.binding(to) };
+        Self::is_encrypted(from) == Self::is_encrypted(to) && (!Self::is_encrypted(to) || self.binding(from) == self.binding(to))
+    }
+
+    /// Checks if a name is internal to `lazy-db`
+    fn is_internal(name: &OsStr) -> bool {
+        name.as_bytes().first() == Some(&b'.')
+    }
+
+    fn encode_name(&self, name: &OsStr) -> OsString {
+        let (mac_key, cipher_key) = match &self.name_keys {
+            Some(x) if !Self::is_internal(name) => x,
+            _ => return name.to_os_string(),
+        };
+
+        // Synthetic nonce from the keyed hash, so the same name always encodes the same way
+        let mut mac = <Blake2sMac256 as Mac>::new_from_slice(mac_key).unwrap();
+        mac.update(name.as_bytes());
+        let nonce = mac.finalize().into_bytes();
+        let mut bytes = name.as_bytes().to_vec();
+        XChaCha20::new(cipher_key.into(), nonce[..NONCE_SIZE].into()).apply_keystream(&mut bytes);
+
+        let mut encoded = String::new();
+        for byte in nonce[..NONCE_SIZE].iter().chain(bytes.iter()) {
+            encoded.push_str(&format!("{byte:02x}"));
+        }
+        encoded.into()
+    }
+
+    fn decode_name(&self, name: &OsStr) -> Option<OsString> {
+        let (mac_key, cipher_key) = match &self.name_keys {
+            Some(x) if !Self::is_internal(name) => x,
+            _ => return Some(name.to_os_string()),
+        };
+
+        let hex = name.as_bytes();
+        if hex.len() < NONCE_SIZE * 2 || !hex.len().is_multiple_of(2) { return None };
+        let bytes = hex.chunks(2)
+            .map(|x| u8::from_str_radix(std::str::from_utf8(x).ok()?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        let (nonce, name) = bytes.split_at(NONCE_SIZE);
+        let mut name = name.to_vec();
+        XChaCha20::new(cipher_key.into(), nonce.into()).apply_keystream(&mut name);
+
+        // Checks the name against it's keyed hash
+        let mut mac = <Blake2sMac256 as Mac>::new_from_slice(mac_key).unwrap();
+        mac.update(&name);
+        if mac.finalize().into_bytes()[..NONCE_SIZE] != *nonce { return None };
+        Some(OsString::from_vec(name))
+    }
+
+    /// Maps a path to where it's actually stored within the inner `Storage`
+    fn map_path(&self, path: &Path) -> PathBuf {
+        if self.name_keys.is_none() { return path.to_path_buf() };
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(x) => x,
+            Err(_) => return path.to_path_buf(),
+        };
+
+        let mut mapped = self.root.clone();
+        for component in relative.components() {
+            match component {
+                Component::Normal(x) => mapped.push(self.encode_name(x)),
+                x => mapped.push(x),
+            }
+        }
+        mapped
+    }
+
+    /// Checks if the contents of the file at the path are encrypted
+    fn is_encrypted(path: &Path) -> bool {
+        !path.file_name().map(Self::is_internal).unwrap_or(false)
+    }
+}
+
+impl Storage for EncryptedStorage {
+    fn is_file(&self, path: &Path) -> bool {
+        self.inner.is_file(&self.map_path(path))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.inner.is_dir(&self.map_path(path))
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.inner.create_dir_all(&self.map_path(path))
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.inner.remove_file(&self.map_path(path))
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.inner.remove_dir_all(&self.map_path(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<StorageEntry>> {
+        Ok(self.inner.read_dir(&self.map_path(path))?
+            .into_iter()
+            .filter_map(|x| Some(StorageEntry {
+                name: self.decode_name(&x.name)?,
+                is_dir: x.is_dir,
+            })).collect())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        if !self.keeps_contents(from, to) {
+            // Re-encrypted for the new path (directories are checked file by file)
+            crate::lazy_container::copy_item(self, from, self, to)?;
+            return if self.is_dir(from) { self.remove_dir_all(from) } else { self.remove_file(from) };
+        }
+        self.inner.rename(&self.map_path(from), &self.map_path(to))
+    }
+
+    fn link(&self, from: &Path, to: &Path) -> io::Result<()> {
+        // The contents are shared as they're stored (still encrypted), unless they need re-encrypting for the new path
+        if !self.keeps_contents(from, to) { return crate::lazy_container::copy_item(self, from, self, to) };
+        self.inner.link(&self.map_path(from), &self.map_path(to))
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        let mut reader = self.inner.open_read(&self.map_path(path))?;
+        if !Self::is_encrypted(path) { return Ok(reader) };
+
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        match self.decrypt(&bytes, &self.binding(path)) {
+            Some(x) => Ok(Box::new(Cursor::new(x))),
+            None => Err(Error::new(ErrorKind::InvalidData, format!("Failed to decrypt '{}'", path.to_string_lossy()))),
+        }
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn StorageWriter>> {
+        let writer = self.inner.open_write(&self.map_path(path))?;
+        if !Self::is_encrypted(path) { return Ok(writer) };
+
+        Ok(Box::new(EncryptedWriter {
+            inner: writer,
+            cipher: self.cipher.clone(),
+            aad: self.binding(path),
+            buffer: Vec::new(),
+        }))
+    }
+
+    fn backing(&self) -> Option<&dyn Storage> {
+        Some(self.inner.as_ref())
+    }
+}
+
+/// Buffers the file's contents until it is committed, then encrypts them into the inner writer
+struct EncryptedWriter {
+    inner: Box<dyn StorageWriter>,
+    cipher: XChaCha20Poly1305,
+    aad: Vec<u8>,
+    buffer: Vec<u8>,
+}
+
+impl Write for EncryptedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl StorageWriter for EncryptedWriter {
+    fn commit(mut self: Box<Self>) -> io::Result<()> {
+        let bytes = EncryptedStorage::encrypt(&self.cipher, &self.buffer, &self.aad)?;
+        self.inner.write_all(&bytes)?;
+        self.inner.commit()
+    }
+}
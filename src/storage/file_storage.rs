@@ -0,0 +1,156 @@
+use super::*;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A `Storage` that directly uses the filesystem
+/// 
+/// Files are written to a temporary sibling (starting with `.`) which is only renamed over the file once the writer is committed.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FileStorage {
+    durability: Durability,
+}
+
+impl FileStorage {
+    /// Constructs a `FileStorage` that syncs committed files to the disk according to the `Durability`
+    pub fn new(durability: Durability) -> Self {
+        Self { durability }
+    }
+
+    /// Returns how committed files are synced to the disk
+    #[inline]
+    pub fn durability(&self) -> Durability {
+        self.durability
+    }
+}
+
+/// Generates the path of a unique temporary sibling of a file
+fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}-{}.tmp", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+    path.with_file_name(name)
+}
+
+/// Syncs the directory entries of a directory (so that renames within it are durable)
+fn sync_dir(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    fs::File::open(path)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+impl Storage for FileStorage {
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir_all(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<StorageEntry>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            entries.push(StorageEntry {
+                name: entry.file_name(),
+                is_dir: entry.file_type()?.is_dir(),
+            });
+        }
+        Ok(entries)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(fs::File::open(path)?))
+    }
+
+    fn file_len(&self, path: &Path) -> io::Result<u64> {
+        Ok(fs::metadata(path)?.len())
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn StorageWriter>> {
+        if path.is_dir() { return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("'{}' is a directory", path.to_string_lossy()))) };
+        let temp = temp_path(path);
+        Ok(Box::new(FileWriter {
+            file: Some(fs::File::create(&temp)?),
+            temp,
+            path: path.to_path_buf(),
+            durability: self.durability,
+        }))
+    }
+
+    fn link(&self, from: &Path, to: &Path) -> io::Result<()> {
+        if to.is_file() { fs::remove_file(to)? };
+        match fs::hard_link(from, to) {
+            Ok(()) => Ok(()),
+            // Not every filesystem supports hard links, and they can't cross filesystems
+            Err(e) if matches!(e.kind(), io::ErrorKind::Unsupported | io::ErrorKind::CrossesDevices) => fs::copy(from, to).map(|_| ()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Writes to a temporary sibling of the file, which replaces the file once committed (or is removed if never committed)
+struct FileWriter {
+    file: Option<fs::File>,
+    temp: PathBuf,
+    path: PathBuf,
+    durability: Durability,
+}
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.as_mut().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.as_mut().unwrap().flush()
+    }
+}
+
+impl StorageWriter for FileWriter {
+    fn commit(mut self: Box<Self>) -> io::Result<()> {
+        let file = self.file.take().unwrap();
+        match self.durability {
+            Durability::None => (),
+            Durability::Flush => file.sync_data()?,
+            Durability::Fsync => file.sync_all()?,
+        }
+        drop(file); // Closed before renaming
+
+        fs::rename(&self.temp, &self.path)?;
+        if self.durability == Durability::Fsync {
+            match self.path.parent() {
+                Some(x) if !x.as_os_str().is_empty() => sync_dir(x)?,
+                _ => sync_dir(Path::new("."))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FileWriter {
+    fn drop(&mut self) {
+        // Wasn't committed (or failed to be), so the old file is left as it was
+        if self.temp.exists() { let _ = fs::remove_file(&self.temp); }
+    }
+}
@@ -0,0 +1,185 @@
+use super::*;
+use std::collections::HashMap;
+use std::io::{Cursor, Error, ErrorKind};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+type Nodes = Arc<RwLock<HashMap<PathBuf, Node>>>;
+
+enum Node {
+    File(Arc<[u8]>),
+    Dir,
+}
+
+/// A `Storage` that keeps all of it's files and directories in memory (within a `HashMap`)
+///
+/// Cloning a `MemoryStorage` shares the same underlying files
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    nodes: Nodes,
+}
+
+impl MemoryStorage {
+    /// Constructs a new, empty `MemoryStorage`
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Checks that the parent of a path is an existing directory
+fn check_parent(nodes: &HashMap<PathBuf, Node>, path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => match nodes.get(parent) {
+            Some(Node::Dir) => Ok(()),
+            _ => Err(Error::new(ErrorKind::NotFound, format!("Directory '{}' not found", parent.to_string_lossy()))),
+        },
+        _ => Ok(()),
+    }
+}
+
+fn not_found(path: &Path) -> Error {
+    Error::new(ErrorKind::NotFound, format!("'{}' not found", path.to_string_lossy()))
+}
+
+impl Storage for MemoryStorage {
+    fn is_file(&self, path: &Path) -> bool {
+        matches!(self.nodes.read().unwrap().get(path), Some(Node::File(_)))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        matches!(self.nodes.read().unwrap().get(path), Some(Node::Dir))
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes.write().unwrap();
+        for ancestor in path.ancestors().filter(|x| !x.as_os_str().is_empty()) {
+            match nodes.get(ancestor) {
+                Some(Node::Dir) => break,
+                Some(Node::File(_)) => return Err(Error::new(ErrorKind::AlreadyExists, format!("'{}' is a file", ancestor.to_string_lossy()))),
+                None => { nodes.insert(ancestor.to_path_buf(), Node::Dir); },
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes.write().unwrap();
+        match nodes.get(path) {
+            Some(Node::File(_)) => { nodes.remove(path); Ok(()) },
+            _ => Err(not_found(path)),
+        }
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes.write().unwrap();
+        if !matches!(nodes.get(path), Some(Node::Dir)) { return Err(not_found(path)) };
+        nodes.retain(|x, _| !x.starts_with(path));
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<StorageEntry>> {
+        let nodes = self.nodes.read().unwrap();
+        if !matches!(nodes.get(path), Some(Node::Dir)) { return Err(not_found(path)) };
+        Ok(nodes.iter()
+            .filter(|(x, _)| x.parent() == Some(path))
+            .filter_map(|(x, node)| Some(StorageEntry {
+                name: x.file_name()?.to_os_string(),
+                is_dir: matches!(node, Node::Dir),
+            })).collect())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes.write().unwrap();
+        let is_dir = match nodes.get(from) {
+            Some(node) => matches!(node, Node::Dir),
+            None => return Err(not_found(from)),
+        };
+        check_parent(&nodes, to)?;
+        if from == to { return Ok(()) };
+        if to.starts_with(from) { return Err(Error::new(ErrorKind::InvalidInput, format!("Cannot move '{}' into itself", from.to_string_lossy()))) };
+
+        // Same rules as the filesystem for what may be replaced
+        match nodes.get(to) {
+            Some(Node::Dir) if !is_dir => return Err(Error::new(ErrorKind::AlreadyExists, format!("'{}' is a directory", to.to_string_lossy()))),
+            Some(Node::File(_)) if is_dir => return Err(Error::new(ErrorKind::AlreadyExists, format!("'{}' is a file", to.to_string_lossy()))),
+            Some(Node::Dir) if nodes.keys().any(|x| x.parent() == Some(to)) => return Err(Error::new(ErrorKind::AlreadyExists, format!("Directory '{}' is not empty", to.to_string_lossy()))),
+            _ => (),
+        }
+
+        let moved: Vec<PathBuf> = nodes.keys().filter(|x| x.starts_with(from)).cloned().collect();
+        for path in moved {
+            let node = nodes.remove(&path).unwrap();
+            let relative = path.strip_prefix(from).unwrap();
+            nodes.insert(if relative.as_os_str().is_empty() { to.to_path_buf() } else { to.join(relative) }, node);
+        }
+        Ok(())
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        match self.nodes.read().unwrap().get(path) {
+            Some(Node::File(bytes)) => Ok(Box::new(Cursor::new(bytes.clone()))),
+            _ => Err(not_found(path)),
+        }
+    }
+
+    fn file_len(&self, path: &Path) -> io::Result<u64> {
+        match self.nodes.read().unwrap().get(path) {
+            Some(Node::File(bytes)) => Ok(bytes.len() as u64),
+            _ => Err(not_found(path)),
+        }
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn StorageWriter>> {
+        let nodes = self.nodes.read().unwrap();
+        check_parent(&nodes, path)?;
+        if let Some(Node::Dir) = nodes.get(path) {
+            return Err(Error::new(ErrorKind::AlreadyExists, format!("'{}' is a directory", path.to_string_lossy())));
+        }
+        Ok(Box::new(MemoryWriter {
+            nodes: self.nodes.clone(),
+            path: path.to_path_buf(),
+            buffer: Vec::new(),
+        }))
+    }
+
+    fn link(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes.write().unwrap();
+        let bytes = match nodes.get(from) {
+            Some(Node::File(bytes)) => bytes.clone(),
+            _ => return Err(not_found(from)),
+        };
+        check_parent(&nodes, to)?;
+        if let Some(Node::Dir) = nodes.get(to) {
+            return Err(Error::new(ErrorKind::AlreadyExists, format!("'{}' is a directory", to.to_string_lossy())));
+        }
+        nodes.insert(to.to_path_buf(), Node::File(bytes));
+        Ok(())
+    }
+}
+
+/// Buffers the file's contents until it is committed into the `MemoryStorage`
+struct MemoryWriter {
+    nodes: Nodes,
+    path: PathBuf,
+    buffer: Vec<u8>,
+}
+
+impl Write for MemoryWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl StorageWriter for MemoryWriter {
+    fn commit(self: Box<Self>) -> io::Result<()> {
+        let mut nodes = self.nodes.write().unwrap();
+        check_parent(&nodes, &self.path)?;
+        nodes.insert(self.path, Node::File(self.buffer.into()));
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+use super::*;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{Error, ErrorKind, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/* `.wal` record format
+ * - tag (u8)
+ * - length of the path relative to the root (u32) and the path (it's raw bytes on unix, otherwise utf8)
+ * - for writes: length of the contents (u64) and the contents
+ * - FNV-1a hash of all of the above (u64), so a torn record at the end is ignored
+ */
+
+/// Name of the write-ahead log in the database's root
+pub const WAL_FILE: &str = ".wal";
+/// Size the log may grow to before it is checkpointed
+const CHECKPOINT_SIZE: u64 = 8 * 1024 * 1024;
+
+const WRITE: u8 = 0;
+const REMOVE_FILE: u8 = 1;
+const REMOVE_DIR_ALL: u8 = 2;
+const CREATE_DIR_ALL: u8 = 3;
+
+/// A `Storage` that appends every modification to a write-ahead log (and syncs it) before applying it to an inner, filesystem based `Storage`
+///
+/// If the process or system crashes, the modifications in the log are replayed the next time it's opened,
+/// so everything that was logged is applied completely.
+/// Checkpoints sync everything that was applied to the disk and then truncate the log; they happen when the log gets large,
+/// before renames (which are atomic on their own) and when the `WalStorage` is dropped.
+pub struct WalStorage {
+    wal: Arc<Wal>,
+}
+
+/// The state of a `WalStorage`, shared with it's writers
+struct Wal {
+    inner: Arc<dyn Storage>,
+    root: PathBuf,
+    log: Mutex<Log>,
+}
+
+struct Log {
+    file: File,
+    size: u64,
+    /// Paths modified since the last checkpoint
+    dirty: HashSet<PathBuf>,
+}
+
+/// Hashes bytes with 64-bit FNV-1a
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, x| (hash ^ *x as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Syncs the file or directory at a path to the disk (if it still exists)
+fn sync_path(path: &Path) -> io::Result<()> {
+    match File::open(path) {
+        Ok(file) => file.sync_all(),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        #[cfg(not(unix))]
+        Err(_) if path.is_dir() => Ok(()), // Directories can't be opened everywhere
+        Err(e) => Err(e),
+    }
+}
+
+/// Encodes a relative path for the log, without losing any of it (see `decode_path`)
+fn encode_path(path: &Path) -> io::Result<&[u8]> {
+    #[cfg(unix)]
+    return Ok(std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()));
+    #[cfg(not(unix))]
+    path.to_str().map(str::as_bytes)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("'{}' isn't valid utf8", path.to_string_lossy())))
+}
+
+/// Decodes a path encoded with `encode_path`
+fn decode_path(bytes: &[u8]) -> Option<PathBuf> {
+    #[cfg(unix)]
+    return Some(PathBuf::from(<std::ffi::OsStr as std::os::unix::ffi::OsStrExt>::from_bytes(bytes)));
+    #[cfg(not(unix))]
+    std::str::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+impl WalStorage {
+    /// Opens the write-ahead log in a database's root on the filesystem, replaying any modifications left in it by a crash
+    pub fn open(inner: Arc<dyn Storage>, root: impl AsRef<Path>) -> io::Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        let file = OpenOptions::new().read(true).append(true).create(true).open(root.join(WAL_FILE))?;
+        let wal = Wal {
+            inner,
+            root,
+            log: Mutex::new(Log { file, size: 0, dirty: HashSet::new() }),
+        };
+
+        wal.replay()?;
+        Ok(Self { wal: Arc::new(wal) })
+    }
+}
+
+impl Wal {
+    /// Applies all of the complete records in the log (again), then checkpoints
+    fn replay(&self) -> io::Result<()> {
+        let mut log = self.log.lock().unwrap();
+        let mut bytes = Vec::new();
+        log.file.seek(SeekFrom::Start(0))?;
+        io::Read::read_to_end(&mut log.file, &mut bytes)?;
+
+        let mut rest = bytes.as_slice();
+        while let Some((tag, path, contents, length)) = Self::parse_record(rest) {
+            let path = self.root.join(path);
+            let result = match tag {
+                WRITE => self.inner.open_write(&path).and_then(|mut writer| {
+                    writer.write_all(contents)?;
+                    writer.commit()
+                }),
+                REMOVE_FILE => if self.inner.is_file(&path) { self.inner.remove_file(&path) } else { Ok(()) },
+                REMOVE_DIR_ALL => if self.inner.is_dir(&path) { self.inner.remove_dir_all(&path) } else { Ok(()) },
+                CREATE_DIR_ALL => self.inner.create_dir_all(&path),
+                _ => break,
+            };
+            match result {
+                // The modification failed the first time too (like writing into a missing container)
+                Err(e) if e.kind() == ErrorKind::NotFound => (),
+                x => x?,
+            }
+            log.dirty.insert(path);
+            rest = &rest[length..];
+        }
+
+        self.checkpoint_log(&mut log)
+    }
+
+    /// Parses the record at the start of the bytes; returns it's tag, path, contents and length (or `None` if it's incomplete)
+    fn parse_record(bytes: &[u8]) -> Option<(u8, PathBuf, &[u8], usize)> {
+        let tag = *bytes.first()?;
+        let path_length = u32::from_le_bytes(bytes.get(1..5)?.try_into().ok()?) as usize;
+        let path = decode_path(bytes.get(5..5 + path_length)?)?;
+        let mut end = 5 + path_length;
+
+        let mut contents: &[u8] = &[];
+        if tag == WRITE {
+            let length = u64::from_le_bytes(bytes.get(end..end + 8)?.try_into().ok()?) as usize;
+            contents = bytes.get(end + 8..(end + 8).checked_add(length)?)?;
+            end += 8 + length;
+        }
+
+        let hash = u64::from_le_bytes(bytes.get(end..end + 8)?.try_into().ok()?);
+        if hash != fnv1a(&bytes[..end]) { return None };
+        Some((tag, path, contents, end + 8))
+    }
+
+    /// Appends a record to the log and syncs it, then applies the modification while the log is still locked
+    fn record<T>(&self, tag: u8, path: &Path, contents: &[u8], apply: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+        let relative = path.strip_prefix(&self.root)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, format!("'{}' is outside of the database", path.to_string_lossy())))?;
+        let relative = encode_path(relative)?;
+
+        let mut record = vec![tag];
+        record.extend_from_slice(&(relative.len() as u32).to_le_bytes());
+        record.extend_from_slice(relative);
+        if tag == WRITE {
+            record.extend_from_slice(&(contents.len() as u64).to_le_bytes());
+            record.extend_from_slice(contents);
+        }
+        record.extend_from_slice(&fnv1a(&record).to_le_bytes());
+
+        let mut log = self.log.lock().unwrap();
+        log.file.write_all(&record)?;
+        log.file.sync_data()?;
+        log.size += record.len() as u64;
+        let result = apply()?;
+        log.dirty.insert(path.to_path_buf());
+
+        if log.size >= CHECKPOINT_SIZE { self.checkpoint_log(&mut log)? };
+        Ok(result)
+    }
+
+    /// Syncs everything modified since the last checkpoint to the disk, then truncates the log
+    fn checkpoint_log(&self, log: &mut Log) -> io::Result<()> {
+        let mut dirs = HashSet::new();
+        for path in log.dirty.drain() {
+            sync_path(&path)?;
+            if let Some(parent) = path.parent() { dirs.insert(parent.to_path_buf()); }
+        }
+        for dir in dirs { sync_path(&dir)? };
+
+        log.file.set_len(0)?;
+        log.file.sync_all()?;
+        log.size = 0;
+        Ok(())
+    }
+}
+
+impl Storage for WalStorage {
+    fn is_file(&self, path: &Path) -> bool {
+        self.wal.inner.is_file(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.wal.inner.is_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        if self.wal.inner.is_dir(path) { return Ok(()) };
+        self.wal.record(CREATE_DIR_ALL, path, &[], || self.wal.inner.create_dir_all(path))
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.wal.record(REMOVE_FILE, path, &[], || self.wal.inner.remove_file(path))
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.wal.record(REMOVE_DIR_ALL, path, &[], || self.wal.inner.remove_dir_all(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<StorageEntry>> {
+        self.wal.inner.read_dir(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        // Renames can't be replayed safely, so the log is cleared before and the rename is synced straight away
+        let mut log = self.wal.log.lock().unwrap();
+        self.wal.checkpoint_log(&mut log)?;
+        self.wal.inner.rename(from, to)?;
+        for path in [from.parent(), to.parent()].into_iter().flatten() { sync_path(path)? };
+        Ok(())
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        self.wal.inner.open_read(path)
+    }
+
+    fn file_len(&self, path: &Path) -> io::Result<u64> {
+        self.wal.inner.file_len(path)
+    }
+
+    fn link(&self, from: &Path, to: &Path) -> io::Result<()> {
+        // Links can't be replayed either, so they're only made once everything logged has been checkpointed
+        let mut log = self.wal.log.lock().unwrap();
+        if log.size > 0 { self.wal.checkpoint_log(&mut log)? };
+        self.wal.inner.link(from, to)?;
+        log.dirty.insert(to.to_path_buf());
+        Ok(())
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn StorageWriter>> {
+        if self.wal.inner.is_dir(path) { return Err(Error::new(ErrorKind::AlreadyExists, format!("'{}' is a directory", path.to_string_lossy()))) };
+        Ok(Box::new(WalWriter {
+            wal: self.wal.clone(),
+            path: path.to_path_buf(),
+            buffer: Vec::new(),
+        }))
+    }
+
+    fn backing(&self) -> Option<&dyn Storage> {
+        Some(self.wal.inner.as_ref())
+    }
+
+    fn checkpoint(&self) -> io::Result<()> {
+        self.wal.checkpoint_log(&mut self.wal.log.lock().unwrap())
+    }
+}
+
+impl Drop for WalStorage {
+    fn drop(&mut self) {
+        let _ = self.checkpoint();
+    }
+}
+
+/// Buffers the file's contents until it is committed, then logs and writes them into the inner `Storage`
+struct WalWriter {
+    wal: Arc<Wal>,
+    path: PathBuf,
+    buffer: Vec<u8>,
+}
+
+impl Write for WalWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl StorageWriter for WalWriter {
+    fn commit(self: Box<Self>) -> io::Result<()> {
+        let wal = &self.wal;
+        wal.record(WRITE, &self.path, &self.buffer, || {
+            let mut writer = wal.inner.open_write(&self.path)?;
+            writer.write_all(&self.buffer)?;
+            writer.commit()
+        })
+    }
+}
@@ -1,11 +1,13 @@
 pub use error::*;
 use std::fmt;
+use std::sync::Arc;
 
-#[derive(Debug)]
+/// A version of `lazy-db` (or of anything else), compared component by component
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Version {
-    pub major: u8,
-    pub minor: u8,
-    pub build: u8,
+    pub major: u32,
+    pub minor: u32,
+    pub build: u32,
 }
 
 impl fmt::Display for Version {
@@ -14,8 +16,20 @@ impl fmt::Display for Version {
     }
 }
 
+/// Parses a number at compile time (used for the package version)
+pub(crate) const fn parse_const(string: &str) -> u32 {
+    let bytes = string.as_bytes();
+    let mut number = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        number = number * 10 + (bytes[i] - b'0') as u32;
+        i += 1;
+    }
+    number
+}
+
 impl Version {
-    pub const fn new(major: u8, minor: u8, build: u8) -> Self {
+    pub const fn new(major: u32, minor: u32, build: u32) -> Self {
         Self {
             major,
             minor,
@@ -30,12 +44,12 @@ impl Version {
         if parts.len() != 3 {
             return Err(VersionError::InvalidSeparator(format!("Expected 2 '.' separators within version, got {}", parts.len() - 1)));
         }
-        
+
         // Checks for valid numbers
-        let major = parts[0].parse::<u8>().map_err(|_| VersionError::InvalidVersion)?;
-        let minor = parts[1].parse::<u8>().map_err(|_| VersionError::InvalidVersion)?;
-        let build = parts[2].parse::<u8>().map_err(|_| VersionError::InvalidVersion)?;
-        
+        let major = parts[0].parse::<u32>().map_err(|_| VersionError::InvalidVersion)?;
+        let minor = parts[1].parse::<u32>().map_err(|_| VersionError::InvalidVersion)?;
+        let build = parts[2].parse::<u32>().map_err(|_| VersionError::InvalidVersion)?;
+
         // Builds version
         Ok(Version::new(major, minor, build))
     }
@@ -47,6 +61,215 @@ impl Version {
     pub fn is_compatible_or_else<F: FnOnce()>(&self, other: &Self, f: F) {
         if !self.is_compatible(other) { f() }
     }
+
+    /// Encodes the version as it's stored in a database's `.meta` file
+    ///
+    /// It's 3 bytes (one per component), which every version of `lazy-db` can read and check.
+    /// Only components over 255 need 12 bytes (little endian `u32`s), which older versions of `lazy-db` reject as an invalid version.
+    pub(crate) fn to_bytes(self) -> Vec<u8> {
+        match (u8::try_from(self.major), u8::try_from(self.minor), u8::try_from(self.build)) {
+            (Ok(major), Ok(minor), Ok(build)) => vec![major, minor, build],
+            _ => [self.major, self.minor, self.build].iter().flat_map(|x| x.to_le_bytes()).collect(),
+        }
+    }
+
+    /// Decodes a version stored in a database's `.meta` file (see `Version::to_bytes`)
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let number = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        match bytes.len() {
+            3 => Some(Self::new(bytes[0] as u32, bytes[1] as u32, bytes[2] as u32)),
+            12 => Some(Self::new(number(0), number(4), number(8))),
+            _ => None,
+        }
+    }
+
+    /// The components of the version, for comparing just the first few
+    fn components(&self) -> [u32; 3] {
+        [self.major, self.minor, self.build]
+    }
+}
+
+/// How a comparator in a `VersionReq` compares versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    /// `~1.2.3` allows changes to the build only (or to the minor, if it's left out)
+    Tilde,
+    /// `^1.2.3` allows changes that don't modify the left-most non-zero component (the default, like cargo)
+    Caret,
+}
+
+/// One comparison in a `VersionReq` against a (possibly partial) version
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    /// The components given; missing components match anything
+    parts: Vec<u32>,
+}
+
+impl Comparator {
+    fn parse(string: &str) -> Result<Self, VersionError> {
+        let string = string.trim();
+        let (op, rest) = [(">=", Op::GreaterEq), ("<=", Op::LessEq), (">", Op::Greater), ("<", Op::Less), ("=", Op::Exact), ("~", Op::Tilde), ("^", Op::Caret)]
+            .into_iter()
+            .find_map(|(prefix, op)| string.strip_prefix(prefix).map(|rest| (op, rest)))
+            .unwrap_or((Op::Caret, string));
+
+        let mut parts = Vec::new();
+        for part in rest.trim().split('.') {
+            if part == "*" || part == "x" { break };
+            parts.push(part.parse::<u32>().map_err(|_| VersionError::InvalidVersion)?);
+        }
+        if parts.len() > 3 { return Err(VersionError::InvalidSeparator(format!("Expected at most 2 '.' separators within '{rest}'"))) };
+        Ok(Self { op, parts })
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        let n = self.parts.len();
+        let prefix = &version.components()[..n];
+        let padded = || {
+            let mut padded = [0; 3];
+            padded[..n].copy_from_slice(&self.parts);
+            padded
+        };
+
+        match self.op {
+            Op::Exact => prefix == self.parts.as_slice(),
+            Op::Greater => prefix > self.parts.as_slice(),
+            Op::GreaterEq => prefix >= self.parts.as_slice(),
+            Op::Less => prefix < self.parts.as_slice(),
+            Op::LessEq => prefix <= self.parts.as_slice(),
+            Op::Tilde => {
+                let fixed = n.min(2);
+                version.components() >= padded() && version.components()[..fixed] == self.parts[..fixed]
+            },
+            Op::Caret => {
+                // Everything up to (and including) the first non-zero component given is fixed
+                let fixed = self.parts.iter().position(|x| *x != 0).map(|x| x + 1).unwrap_or(n);
+                version.components() >= padded() && version.components()[..fixed] == self.parts[..fixed]
+            },
+        }
+    }
+}
+
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self.op {
+            Op::Exact => "=",
+            Op::Greater => ">",
+            Op::GreaterEq => ">=",
+            Op::Less => "<",
+            Op::LessEq => "<=",
+            Op::Tilde => "~",
+            Op::Caret => "^",
+        };
+        let parts: Vec<String> = self.parts.iter().map(|x| x.to_string()).collect();
+        if parts.is_empty() { write!(f, "*") } else { write!(f, "{op}{}", parts.join(".")) }
+    }
+}
+
+/// A semver-like range of versions, like `>=1.2, <2` or `^1.5.4`
+///
+/// Each comparator is separated by a `,` and all of them must match.
+/// Missing (or `*`) components match anything and versions without an operator are treated like `^`, as in cargo.
+/// ```rust
+/// use lazy_db::version::*;
+/// let req = VersionReq::parse(">=1.2, <2").unwrap();
+/// assert!(req.matches(&Version::new(1, 5, 4)));
+/// assert!(!req.matches(&Version::new(2, 0, 0)));
+/// assert!(VersionReq::parse("~1.2.3").unwrap().matches(&Version::new(1, 2, 9)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionReq {
+    comparators: Vec<Comparator>,
+}
+
+impl VersionReq {
+    pub fn parse(req: &str) -> Result<Self, VersionError> {
+        let comparators = req.split(',')
+            .filter(|x| !x.trim().is_empty())
+            .map(Comparator::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { comparators })
+    }
+
+    /// Checks if a version is within the range
+    pub fn matches(&self, version: &Version) -> bool {
+        self.comparators.iter().all(|x| x.matches(version))
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.comparators.is_empty() { return write!(f, "*") };
+        let comparators: Vec<String> = self.comparators.iter().map(|x| x.to_string()).collect();
+        write!(f, "{}", comparators.join(", "))
+    }
+}
+
+/// Checks a database's version for `VersionPolicy::Custom`, given the current version and the database's version
+pub type VersionCheck = Arc<dyn Fn(&Version, &Version) -> bool + Send + Sync>;
+
+/// Decides which versions of `lazy-db` a database may have been written by for it to be opened (see `LazyOptions::version_policy`)
+#[derive(Clone, Default)]
+pub enum VersionPolicy {
+    /// Same major version and a minor version no newer than the current one (see `Version::is_compatible`)
+    #[default]
+    Compatible,
+    /// Only the exact current version
+    Strict,
+    /// Any version with the same major version
+    SameMajor,
+    /// Any version within a range
+    Range(VersionReq),
+    /// Decided by a function, given the current version and the database's version
+    Custom(VersionCheck),
+}
+
+impl VersionPolicy {
+    /// Constructs a policy decided by a function, given the current version and the database's version
+    pub fn custom(f: impl Fn(&Version, &Version) -> bool + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(f))
+    }
+
+    /// Checks if a database written by a version may be opened by the current version
+    pub fn allows(&self, current: &Version, found: &Version) -> bool {
+        match self {
+            Self::Compatible => current.is_compatible(found),
+            Self::Strict => current == found,
+            Self::SameMajor => current.major == found.major,
+            Self::Range(req) => req.matches(found),
+            Self::Custom(f) => f(current, found),
+        }
+    }
+}
+
+impl fmt::Display for VersionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Compatible => write!(f, "compatible"),
+            Self::Strict => write!(f, "strict"),
+            Self::SameMajor => write!(f, "same major"),
+            Self::Range(req) => write!(f, "range '{req}'"),
+            Self::Custom(_) => write!(f, "custom"),
+        }
+    }
+}
+
+impl fmt::Debug for VersionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Compatible => write!(f, "Compatible"),
+            Self::Strict => write!(f, "Strict"),
+            Self::SameMajor => write!(f, "SameMajor"),
+            Self::Range(req) => f.debug_tuple("Range").field(req).finish(),
+            Self::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
 }
 
 pub mod error {
@@ -68,4 +291,4 @@ pub mod error {
     }
 
     impl Error for VersionError {}
-}
\ No newline at end of file
+}
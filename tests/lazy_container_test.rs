@@ -0,0 +1,229 @@
+mod isol;
+use isol::*;
+use lazy_db::*;
+
+#[test]
+fn lazy_container_keys() {
+    let tmp = new_env();
+    let database = LazyDB::init(tmp.get_path().join("database")).unwrap();
+    write_database!((&database) b = new_u8(1)).unwrap();
+    write_database!((&database) a = new_u8(2)).unwrap();
+    write_database!((&database) /c::d = new_u8(3)).unwrap();
+    let container = database.as_container().unwrap();
+
+    // Internal files (`.meta`) must be excluded and keys sorted
+    assert_eq!(container.keys().unwrap(), ["a", "b", "c"]);
+    assert_eq!(container.data_keys().unwrap(), ["a", "b"]);
+    assert_eq!(container.container_keys().unwrap(), ["c"]);
+}
+
+#[test]
+fn lazy_container_entries() {
+    let database = LazyDB::in_memory().unwrap();
+    write_database!((&database) number = new_u8(12)).unwrap();
+    write_database!((&database) /nested::data = new_string("Hello world!")).unwrap();
+
+    let mut entries = database.as_container().unwrap().entries().unwrap();
+    match entries.next().unwrap().unwrap() {
+        Entry::Container(x) => assert_eq!(x.data_keys().unwrap(), ["data"]),
+        Entry::Data(_) => panic!("Expected container"),
+    }
+    match entries.next().unwrap().unwrap() {
+        Entry::Data(x) => assert_eq!(x.collect_u8().unwrap(), 12),
+        Entry::Container(_) => panic!("Expected data"),
+    }
+    assert!(entries.next().is_none());
+}
+
+#[test]
+fn lazy_container_walk() {
+    let database = LazyDB::in_memory().unwrap();
+    write_database!((&database) /people/Dave::age = new_u8(21)).unwrap();
+    write_database!((&database) /people::count = new_u8(1)).unwrap();
+    write_database!((&database) version = new_u8(1)).unwrap();
+    let container = database.as_container().unwrap();
+
+    // Depth-first with containers before their contents
+    let paths: Vec<String> = container.walk().unwrap().map(|x| x.unwrap().0.to_string()).collect();
+    assert_eq!(paths, ["/people", "/people/Dave", "/people/Dave::age", "/people::count", "::version"]);
+
+    // Depth limit
+    let paths: Vec<String> = container.walk().unwrap().max_depth(1).map(|x| x.unwrap().0.to_string()).collect();
+    assert_eq!(paths, ["/people", "::version"]);
+}
+
+#[test]
+fn lazy_container_visit() {
+    #[derive(Default)]
+    struct Counter {
+        depth: usize,
+        max_depth: usize,
+        data: Vec<String>,
+    }
+
+    impl Visitor for Counter {
+        fn enter_container(&mut self, _: &LazyPath, _: &LazyContainer) -> Result<(), LDBError> {
+            self.depth += 1;
+            self.max_depth = self.max_depth.max(self.depth);
+            Ok(())
+        }
+
+        fn visit_data(&mut self, path: &LazyPath, _: LazyData) -> Result<(), LDBError> {
+            self.data.push(path.to_string());
+            Ok(())
+        }
+
+        fn leave_container(&mut self, _: &LazyPath, _: &LazyContainer) -> Result<(), LDBError> {
+            self.depth -= 1;
+            Ok(())
+        }
+    }
+
+    let database = LazyDB::in_memory().unwrap();
+    write_database!((&database) /people/Dave::age = new_u8(21)).unwrap();
+    write_database!((&database) version = new_u8(1)).unwrap();
+
+    let mut counter = Counter::default();
+    database.as_container().unwrap().visit(&mut counter).unwrap();
+    assert_eq!(counter.depth, 0);
+    assert_eq!(counter.max_depth, 3);
+    assert_eq!(counter.data, ["/people/Dave::age", "::version"]);
+}
+
+#[test]
+fn lazy_container_invalid_keys() {
+    let tmp = new_env();
+    let database = LazyDB::init(tmp.get_path().join("database")).unwrap();
+    let container = database.as_container().unwrap();
+
+    for key in ["", "..", "../../etc", "a/../b", "/etc", ".meta", "nul\0"] {
+        assert!(matches!(container.data_writer(key), Err(LDBError::InvalidKey(..))), "{key:?}");
+        assert!(matches!(container.new_container(key), Err(LDBError::InvalidKey(..))), "{key:?}");
+        assert!(matches!(container.read_data(key), Err(LDBError::InvalidKey(..))), "{key:?}");
+    }
+
+    // Nothing may have escaped the database
+    assert!(!tmp.get_path().join("etc").exists());
+}
+
+#[test]
+fn lazy_container_escaped_keys() {
+    let tmp = new_env();
+    let database = LazyDB::init(tmp.get_path().join("database")).unwrap();
+    let container = database.as_container().unwrap();
+
+    // Slashes and percent signs are part of the key rather than nested paths
+    for key in ["a/b", "100%", "back\\slash", "ünïcödé 🦀", ".hidden", ".5"] {
+        LazyData::new_string(container.data_writer(key).unwrap(), key).unwrap();
+        assert_eq!(container.read_data(key).unwrap().collect_string().unwrap(), key);
+    }
+    assert!(container.container_keys().unwrap().is_empty());
+    assert_eq!(container.keys().unwrap(), [".5", ".hidden", "100%", "a/b", "back\\slash", "ünïcödé 🦀"]);
+
+    // Keys starting with '.' are stored apart from the internal files
+    assert!(tmp.get_path().join("database/%2Ehidden").is_file());
+    container.new_container(".lockers").unwrap();
+    assert_eq!(container.container_keys().unwrap(), [".lockers"]);
+    assert!(container.read_container(".lockers").is_ok());
+}
+
+#[test]
+fn lazy_container_legacy_keys() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    drop(LazyDB::init(&path).unwrap());
+
+    // Stored as they are by an older version, before keys were escaped
+    std::fs::remove_file(path.join(".metadata/escaped_keys")).unwrap();
+    let write = |name: &str, value| LazyData::new_u8(LazyWriter::new(std::fs::File::create(path.join(name)).unwrap()), value).unwrap();
+    write("50%", 5);
+    write("50%25", 25);
+    write(".hidden", 1);
+    std::fs::create_dir(path.join("back\\slash")).unwrap();
+    write("back\\slash/100%", 100);
+
+    // Renamed to their escaped keys once, when loaded
+    let database = LazyDB::load_dir(&path).unwrap();
+    let container = database.as_container().unwrap();
+    assert_eq!(container.keys().unwrap(), [".hidden", "50%", "50%25", "back\\slash"]);
+    assert_eq!(container.read_data("50%").unwrap().collect_u8().unwrap(), 5);
+    assert_eq!(container.read_data("50%25").unwrap().collect_u8().unwrap(), 25);
+    assert_eq!(container.read_data(".hidden").unwrap().collect_u8().unwrap(), 1);
+    assert_eq!(database.get("/back\\slash::100%25").unwrap().collect_u8().unwrap(), 100);
+    assert!(path.join("50%2525").is_file());
+    assert!(path.join("back%5Cslash/100%25").is_file());
+    assert!(!path.join(".migration").exists());
+    drop(database);
+
+    // Never again, so escaped names aren't escaped twice
+    let database = LazyDB::load_dir(&path).unwrap();
+    assert_eq!(database.get("::50%25").unwrap().collect_u8().unwrap(), 5);
+    assert!(path.join("50%25").is_file());
+}
+
+#[test]
+fn lazy_container_rename_move() {
+    let tmp = new_env();
+    let database = LazyDB::init(tmp.get_path().join("database")).unwrap();
+    write_database!((&database) /users/Dave::age = new_u8(21)).unwrap();
+    write_database!((&database) /users/Bob::age = new_u8(42)).unwrap();
+    let users = database.as_container().unwrap().get_container("/users").unwrap();
+
+    // Renaming within a container
+    users.rename("Dave", "David", Overwrite::Deny).unwrap();
+    assert_eq!(users.keys().unwrap(), ["Bob", "David"]);
+
+    // Overwrite policies
+    assert!(matches!(users.rename("Bob", "David", Overwrite::Deny), Err(LDBError::AlreadyExists(_))));
+    users.rename("Bob", "David", Overwrite::Skip).unwrap();
+    assert_eq!(database.get("/users/David::age").unwrap().collect_u8().unwrap(), 21);
+    users.rename("Bob", "David", Overwrite::Replace).unwrap();
+    assert_eq!(users.keys().unwrap(), ["David"]);
+    assert_eq!(database.get("/users/David::age").unwrap().collect_u8().unwrap(), 42);
+
+    // Re-parenting
+    let archived = database.get_or_create("/archived").unwrap();
+    users.move_to("David", &archived, "David", Overwrite::Deny).unwrap();
+    assert!(users.keys().unwrap().is_empty());
+    assert_eq!(database.get("/archived/David::age").unwrap().collect_u8().unwrap(), 42);
+    assert!(database.as_container().unwrap().move_to("archived", &archived, "inner", Overwrite::Deny).is_err());
+    assert!(matches!(users.rename("missing", "other", Overwrite::Deny), Err(LDBError::FileNotFound(_))));
+
+    // Onto a container it's within, which must not be removed first
+    let root = database.as_container().unwrap();
+    assert!(matches!(archived.move_to("David", &root, "archived", Overwrite::Replace), Err(LDBError::InvalidPath(..))));
+    assert!(matches!(archived.copy_tree("David", &root, "archived", Overwrite::Replace), Err(LDBError::InvalidPath(..))));
+    assert_eq!(database.get("/archived/David::age").unwrap().collect_u8().unwrap(), 42);
+}
+
+#[test]
+fn lazy_container_copy_tree() {
+    let tmp = new_env();
+    let database = LazyDB::init(tmp.get_path().join("database")).unwrap();
+    write_database!((&database) /users/Dave::age = new_u8(21)).unwrap();
+    write_database!((&database) /users/Dave/pets::cat = new_string("Tom")).unwrap();
+    let root = database.as_container().unwrap();
+
+    // Within the same storage
+    root.copy_tree("users", &root, "backup", Overwrite::Deny).unwrap();
+    assert_eq!(database.get("/backup/Dave/pets::cat").unwrap().collect_string().unwrap(), "Tom");
+    assert_eq!(database.get("/users/Dave::age").unwrap().collect_u8().unwrap(), 21);
+
+    // Across storages (and moving back)
+    let memory = LazyDB::in_memory().unwrap();
+    let memory_root = memory.as_container().unwrap();
+    root.copy_tree("users", &memory_root, "users", Overwrite::Deny).unwrap();
+    assert_eq!(memory.get("/users/Dave::age").unwrap().collect_u8().unwrap(), 21);
+    memory.set("/users/Dave::age", |file| LazyData::new_u8(file, 22)).unwrap();
+    memory_root.move_to("users", &root, "users", Overwrite::Replace).unwrap();
+    assert!(memory_root.keys().unwrap().is_empty());
+    assert_eq!(database.get("/users/Dave::age").unwrap().collect_u8().unwrap(), 22);
+    assert_eq!(database.get("/users/Dave/pets::cat").unwrap().collect_string().unwrap(), "Tom");
+
+    // A replaced container is put back if it's replacement fails (here with a name too long for the filesystem)
+    memory.set(format!("/users::{}", "x".repeat(300)), |file| LazyData::new_u8(file, 0)).unwrap();
+    assert!(memory_root.move_to("users", &root, "users", Overwrite::Replace).is_err());
+    assert_eq!(database.get("/users/Dave::age").unwrap().collect_u8().unwrap(), 22);
+    assert!(!database.path().join(".users.replaced").exists());
+    assert_eq!(memory_root.keys().unwrap(), ["users"]);
+}
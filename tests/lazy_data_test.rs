@@ -15,7 +15,7 @@ macro_rules! test_lazy_data {
             let path = tmp.get_path().join("data.ld");
             let og = $value;
             // Create file
-            let file = FileWrapper::new_writer(File::create(&path).unwrap());
+            let file = LazyWriter::new(File::create(&path).unwrap());
             // Write to file
             LazyData::$func(file, og).unwrap();
             // Load file
@@ -31,7 +31,7 @@ fn lazy_data_new_void() {
     let tmp = new_env();
     let path = tmp.get_path().join("data.ld");
     // Create file
-    let file = FileWrapper::new_writer(File::create(&path).unwrap());
+    let file = LazyWriter::new(File::create(&path).unwrap());
     // Write void
     LazyData::new_void(file, ()).unwrap();
     // Load void file
@@ -55,7 +55,7 @@ fn lazy_data_binary() {
     let path = tmp.get_path().join("data.ld");
     let og_bin = Box::new([12u8, 234, 48, 128]);
     // Create binary file
-    let file = FileWrapper::new_writer(File::create(&path).unwrap());
+    let file = LazyWriter::new(File::create(&path).unwrap());
     LazyData::new_binary(file, og_bin.as_ref()).unwrap();
     // Load binary file
     let new_bin = LazyData::load(path).unwrap().collect_binary().unwrap();
@@ -86,11 +86,25 @@ fn lazy_data_array() {
     let path = tmp.get_path().join("data.ld");
     let og = [32, -42, 86, -12093];
     // Create file
-    let file = FileWrapper::new_writer(File::create(&path).unwrap());
+    let file = LazyWriter::new(File::create(&path).unwrap());
     // Write to file
     LazyData::new_i32_array(file, &og).unwrap();
     // Load file
     let new = LazyData::load(path).unwrap().collect_i32_array().unwrap();
     // Values must be the same
     let _ = og.iter().enumerate().map(|(i, x)| assert_eq!(*x, new[i]));
-}
\ No newline at end of file
+}
+
+#[test]
+fn lazy_data_large_array() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("data.ld");
+    let og: Vec<u32> = (0..10000).collect();
+    // Write an array larger than the reader's buffer
+    let file = LazyWriter::new(File::create(&path).unwrap());
+    LazyData::new_u32_array(file, &og).unwrap();
+    // Load file
+    let new = LazyData::load(path).unwrap().collect_u32_array().unwrap();
+    // Values must be the same
+    assert_eq!(og.as_slice(), new.as_ref());
+}
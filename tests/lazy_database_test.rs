@@ -11,6 +11,7 @@ fn lazy_database() {
     // Writing to the database
     let database = LazyDB::init(&path).unwrap();
     write_database!((database) data = new_string(&og_string)).unwrap(); // Writes to database with macro
+    drop(database); // Releases the database's lock
 
     // Read from the database
     let database = LazyDB::load_dir(path).unwrap();
@@ -29,6 +30,7 @@ fn lazy_database_compile() {
     // Writing to the database and compiling
     let database = LazyDB::init_db(&path).unwrap();
     write_database!((&database) ("data") = new_string(&og_string)).unwrap(); // Writes to database with macro
+    drop(database); // Compiles and releases the database's lock
     let path = path.with_extension("ldb");
 
     // Read from the database
@@ -48,6 +50,7 @@ fn lazy_database_compile_nested() {
     // Writing to the database and compiling
     let database = LazyDB::init_db(&path).unwrap();
     write_database!((&database) /("nested")::data = new_string(&og_string)).unwrap(); // Writes to database with macro
+    drop(database); // Compiles and releases the database's lock
     let path = path.with_extension("ldb");
 
     // Read from the database
@@ -58,6 +61,116 @@ fn lazy_database_compile_nested() {
     assert_eq!(og_string, new_string);
 }
 
+#[test]
+fn lazy_database_search_no_side_effects() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let database = LazyDB::init(&path).unwrap();
+
+    // Searching for missing containers reports the first missing one and creates nothing
+    match search_database!((database) /missing/deeper::data) {
+        Err(LDBError::DirNotFound(x)) => assert_eq!(x, path.join("missing")),
+        _ => panic!("expected `DirNotFound`"),
+    }
+    assert!(search_database!((database) /missing).is_err());
+    assert!(!path.join("missing").exists());
+
+    // Creation is explicit
+    let container = database.get_or_create("/missing/deeper").unwrap();
+    write_container!((container) data = new_u8(7)).unwrap();
+    assert_eq!(search_database!((database) /missing/deeper::data).unwrap().collect_u8().unwrap(), 7);
+}
+
+#[test]
+fn lazy_database_open_archive() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let og_string = String::from("Hello world!");
+
+    // Writing to the database and compiling
+    {
+        let database = LazyDB::init_db(&path).unwrap();
+        write_database!((&database) /nested::data = new_string(&og_string)).unwrap();
+        write_database!((&database) /nested::number = new_u16(1234)).unwrap();
+    }
+    let path = path.with_extension("ldb");
+
+    // Read straight from the archive
+    let database = LazyDB::open_archive(&path).unwrap();
+    let new_string = search_container!((database.as_container().unwrap()) /nested::data).unwrap().collect_string().unwrap();
+    let number = search_container!((database.as_container().unwrap()) /nested::number).unwrap().collect_u16().unwrap();
+    assert_eq!(og_string, new_string);
+    assert_eq!(number, 1234);
+
+    // Archive must not be decompiled or modified
+    assert!(!path.with_extension("ldb.modb").exists());
+    assert!(write_database!((&database) data = new_u8(0)).is_err());
+}
+
+#[test]
+fn lazy_database_compile_indexed() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let og_string = String::from("Hello world!");
+
+    // Writing to the database and compiling in the indexed format
+    {
+        let mut database = LazyDB::init_db(&path).unwrap();
+        database.set_format(ArchiveFormat::Indexed);
+        write_database!((&database) /nested::data = new_string(&og_string)).unwrap();
+    }
+    let path = path.with_extension("ldb");
+
+    // Read from the decompiled database
+    {
+        let database = LazyDB::load_db(&path).unwrap();
+        assert_eq!(database.format(), ArchiveFormat::Indexed);
+        let new_string = search_database!((database) /nested::data).unwrap().collect_string().unwrap();
+        assert_eq!(og_string, new_string);
+    }
+
+    // Read straight from the indexed archive
+    let database = LazyDB::open_archive(&path).unwrap();
+    assert_eq!(database.format(), ArchiveFormat::Indexed);
+    let new_string = search_container!((database.as_container().unwrap()) /nested::data).unwrap().collect_string().unwrap();
+    assert_eq!(og_string, new_string);
+}
+
+#[test]
+fn lazy_database_indexed_invalid() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let archive = tmp.get_path().join("database.ldb");
+    let live = LazyDB::init(&path).unwrap();
+    write_database!((&live) /nested::data = new_string("Hello world!")).unwrap();
+    live.compile_as(&archive, ArchiveFormat::Indexed).unwrap();
+    let bytes = std::fs::read(&archive).unwrap();
+    let corrupt = |f: &dyn Fn(&mut Vec<u8>)| {
+        let mut bytes = bytes.clone();
+        f(&mut bytes);
+        std::fs::write(&archive, bytes).unwrap();
+        LazyDB::open_archive(&archive)
+    };
+
+    // Truncated, or with an index outside of the file
+    assert!(corrupt(&|x| x.truncate(20)).is_err());
+    assert!(corrupt(&|x| { let len = x.len(); x[len - 16..len - 8].copy_from_slice(&u64::MAX.to_be_bytes()) }).is_err());
+
+    // A block outside of the file, or one that claims to decompress to more than it could
+    let entry = bytes.windows(11).position(|x| x == b"nested/data").unwrap() + 12;
+    assert!(corrupt(&|x| x[entry + 8..entry + 16].copy_from_slice(&u64::MAX.to_be_bytes())).is_err());
+    let database = corrupt(&|x| x[entry + 16..entry + 24].copy_from_slice(&(1u64 << 40).to_be_bytes())).unwrap();
+    assert!(database.get("/nested::data").is_err());
+    drop(database);
+
+    // Names that can't be stored are refused rather than mangled
+    #[cfg(unix)] {
+        use std::os::unix::ffi::OsStrExt;
+        std::fs::write(path.join(std::ffi::OsStr::from_bytes(b"\xff")), []).unwrap();
+        assert_eq!(live.compile_as(&archive, ArchiveFormat::Indexed).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
+
 fn _lazy_database_stress_test() {
     {let tmp = new_env();
     let path = tmp.get_path().join("stressed_database");
@@ -67,4 +180,322 @@ fn _lazy_database_stress_test() {
         write_database!((&database) /root::(i.to_string()) = new_u64(gen_random())).unwrap();
     }}
     std::io::stdin().read_line(&mut String::new()).unwrap();
-}
\ No newline at end of file
+}
+
+#[test]
+fn lazy_database_locking() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+
+    // Read-write opens are exclusive, waiting for the default timeout
+    let database = LazyDB::init(&path).unwrap();
+    let start = std::time::Instant::now();
+    assert!(matches!(LazyDB::load_dir(&path), Err(LDBError::Locked(_))));
+    assert!(start.elapsed() >= DEFAULT_LOCK_TIMEOUT);
+    let immediate = LazyDB::options().lock_timeout(std::time::Duration::ZERO).clone();
+    assert!(matches!(immediate.load_dir(&path), Err(LDBError::Locked(_))));
+
+    // Opens once it's released within the timeout
+    let closing = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        drop(database);
+    });
+    drop(LazyDB::load_dir(&path).unwrap());
+    closing.join().unwrap();
+
+    // Read-only opens are shared, but exclude read-write ones
+    let archive = tmp.get_path().join("archive.ldb");
+    let database = LazyDB::init_db(&archive).unwrap();
+    assert!(matches!(immediate.load_db(&archive), Err(LDBError::Locked(_))));
+    drop(database);
+    let first = LazyDB::open_archive(&archive).unwrap();
+    let second = LazyDB::open_archive(&archive).unwrap();
+    assert!(matches!(immediate.load_db(&archive), Err(LDBError::Locked(_))));
+    drop((first, second));
+    drop(LazyDB::load_db(&archive).unwrap());
+}
+
+#[test]
+fn lazy_database_shared() {
+    let tmp = new_env();
+    let database = LazyDB::init(tmp.get_path().join("database")).unwrap().shared();
+
+    // Writers to the same key never lose updates, and readers never see half-written values
+    let threads: Vec<_> = (0..8u8).map(|i| {
+        let database = database.clone();
+        std::thread::spawn(move || for _ in 0..25 {
+            database.update("::count", |old, file| {
+                let count = match old { Some(x) => x.collect_u32()?, None => 0 };
+                LazyData::new_u32(file, count + 1)
+            }).unwrap();
+            database.set(format!("/threads::{i}"), |file| LazyData::new_string(file, &"x".repeat(4096))).unwrap();
+            assert_eq!(database.read(format!("/threads::{i}"), |data| data.collect_string()).unwrap().len(), 4096);
+        })
+    }).collect();
+    for thread in threads { thread.join().unwrap() };
+
+    assert_eq!(database.read("::count", |data| data.collect_u32()).unwrap(), 200);
+    database.remove("/threads").unwrap();
+    assert!(database.read("/threads::0", |data| data.collect_string()).is_err());
+}
+
+#[test]
+fn lazy_database_read_only() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+
+    // Writing to the database and compiling
+    let database = LazyDB::init_db(&path).unwrap();
+    write_database!((&database) /nested::data = new_string("Hello world!")).unwrap();
+    drop(database);
+    let path = path.with_extension("ldb");
+    let bytes = std::fs::read(&path).unwrap();
+
+    // Many readers at once, through the usual macros
+    let first = LazyDB::open_read_only(&path).unwrap();
+    let second = LazyDB::open_read_only(&path).unwrap();
+    assert_eq!(search_database!((first) /nested::data).unwrap().collect_string().unwrap(), "Hello world!");
+    assert_eq!(second.as_container().unwrap().get_container("/nested").unwrap().keys().unwrap(), ["data"]);
+    drop((first, second));
+
+    // Nothing is recompiled or decompiled
+    assert_eq!(std::fs::read(&path).unwrap(), bytes);
+    assert!(!path.with_extension("ldb.modb").exists());
+
+    // Directories can be opened read-only too
+    let dir = tmp.get_path().join("dir");
+    let database = LazyDB::init(&dir).unwrap();
+    write_database!((&database) data = new_u8(7)).unwrap();
+    drop(database);
+    let database = LazyDB::open_read_only(&dir).unwrap();
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 7);
+    assert!(matches!(LazyDB::load_dir(&dir), Err(LDBError::Locked(_))));
+}
+
+#[test]
+fn lazy_database_close() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let ldb_path = path.with_extension("ldb");
+
+    // Closing compiles and cleans up
+    let database = LazyDB::init_db(&path).unwrap();
+    assert!(database.is_dirty());
+    write_database!((&database) data = new_u8(1)).unwrap();
+    database.close().unwrap();
+    assert!(ldb_path.is_file());
+    assert!(!path.with_extension("ldb.modb").exists());
+
+    // Nothing written: not recompiled
+    let bytes = std::fs::read(&ldb_path).unwrap();
+    let database = LazyDB::load_db(&ldb_path).unwrap();
+    assert!(!database.is_dirty());
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 1);
+    database.close().unwrap();
+    assert_eq!(std::fs::read(&ldb_path).unwrap(), bytes);
+
+    // Written: recompiled
+    let database = LazyDB::load_db(&ldb_path).unwrap();
+    write_database!((&database) data = new_u8(2)).unwrap();
+    assert!(database.is_dirty());
+    database.close().unwrap();
+    let database = LazyDB::load_db(&ldb_path).unwrap();
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 2);
+    database.close().unwrap();
+
+    // Errors are reported, and the modifiable directory is kept
+    let other = tmp.get_path().join("other");
+    std::fs::create_dir_all(other.with_extension("ldb")).unwrap(); // Can't be compiled over
+    let database = LazyDB::init_db(&other).unwrap();
+    assert!(database.close().is_err());
+    assert!(other.with_extension("ldb.modb").is_dir());
+}
+
+#[test]
+fn lazy_database_drop_error_hook() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    std::fs::create_dir_all(path.with_extension("ldb")).unwrap(); // Can't be compiled over
+
+    let errors = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let hook_errors = errors.clone();
+    let database = LazyDB::options()
+        .on_drop_error(move |path, e| hook_errors.lock().unwrap().push((path.to_path_buf(), e.to_string())))
+        .init_db(&path)
+        .unwrap();
+    drop(database);
+
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, path.with_extension("ldb.modb"));
+}
+
+#[test]
+fn lazy_database_recovery() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database.ldb");
+    let mut options = LazyDB::options();
+    options.locking(false); // Crashes are simulated by forgetting databases, which would leave them locked
+    let database = LazyDB::init_db(&path).unwrap();
+    write_database!((&database) data = new_u8(1)).unwrap();
+    database.close().unwrap();
+
+    // Crashed with modifications: the directory is newer, so it's kept and recompiled
+    let database = options.load_db(&path).unwrap();
+    assert!(database.recovery().is_empty());
+    assert_eq!(database.generation().unwrap(), 1);
+    write_database!((&database) data = new_u8(2)).unwrap();
+    std::mem::forget(database);
+    let database = LazyDB::load_db(&path).unwrap();
+    assert_eq!(database.recovery().state, Some(RecoveredState::Directory { generation: 1 }));
+    assert!(database.is_dirty());
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 2);
+    database.close().unwrap();
+    let old = std::fs::read(&path).unwrap();
+
+    // Crashed, but the compiled file was replaced by a newer one: the stale directory is discarded
+    let database = LazyDB::load_db(&path).unwrap();
+    write_database!((&database) data = new_u8(3)).unwrap();
+    database.close().unwrap();
+    let new = std::fs::read(&path).unwrap();
+    std::fs::write(&path, old).unwrap();
+    std::mem::forget(options.load_db(&path).unwrap());
+    std::fs::write(&path, new).unwrap();
+    // Nothing in it is recovered first (a restore that can't finish would fail the load)
+    let stale = path.with_extension("ldb.modb");
+    std::fs::create_dir_all(stale.join(".snapshots")).unwrap();
+    LazyData::new_string(LazyWriter::new(std::fs::File::create(stale.join(".snapshots/.restoring")).unwrap()), "missing").unwrap();
+    let database = LazyDB::load_db(&path).unwrap();
+    assert_eq!(database.recovery().state, Some(RecoveredState::Compiled { generation: 3 }));
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 3);
+    database.close().unwrap();
+
+    // Crashed with modifications, but loading it is rejected: the directory is kept until it can be loaded
+    let database = options.load_db(&path).unwrap();
+    write_database!((&database) data = new_u8(99)).unwrap();
+    std::mem::forget(database);
+    let rejecting = options.clone().version_policy(version::VersionPolicy::custom(|_, _| false)).clone();
+    assert!(matches!(rejecting.load_db(&path), Err(LDBError::IncompatibleVersion(..))));
+    let database = LazyDB::load_db(&path).unwrap();
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 99);
+    write_database!((&database) data = new_u8(3)).unwrap();
+    database.close().unwrap();
+
+    // Incomplete directories are discarded and stale temporary files are removed
+    let (decompiling, tar) = (path.with_extension("ldb.modb.tmp"), path.with_extension("ldb.tar.tmp"));
+    std::fs::create_dir(path.with_extension("ldb.modb")).unwrap();
+    std::fs::create_dir(&decompiling).unwrap();
+    std::fs::write(decompiling.join(".decompiling"), b"").unwrap();
+    let mut half_tarball = vec![0u8; 300];
+    half_tarball[257..262].copy_from_slice(b"ustar");
+    std::fs::write(&tar, half_tarball).unwrap();
+    let database = LazyDB::load_db(&path).unwrap();
+    assert_eq!(database.recovery().state, Some(RecoveredState::Compiled { generation: 4 }));
+    assert_eq!(database.recovery().removed, [decompiling, tar.clone()]);
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 3);
+    database.close().unwrap();
+    assert!(!tar.exists());
+
+    // Files that weren't written by `lazy-db` are left alone, even if they're named like temporaries
+    let (other, named_like) = (path.with_extension("tmp"), path.with_extension("ldb.tmp"));
+    std::fs::write(&other, b"notes").unwrap();
+    std::fs::write(&named_like, b"notes").unwrap();
+    let database = LazyDB::load_db(&path).unwrap();
+    assert!(database.recovery().is_empty());
+    database.close().unwrap();
+    assert!(other.is_file() && named_like.is_file());
+}
+
+#[test]
+fn lazy_database_archive_path() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("app.db");
+
+    let database = LazyDB::init_db(&path).unwrap();
+    assert_eq!(database.archive_path(), Some(path.as_path()));
+    write_database!((&database) data = new_u8(1)).unwrap();
+    drop(database);
+    assert!(path.is_file());
+
+    // Written back to exactly where it was loaded from
+    let mut database = LazyDB::load_db(&path).unwrap();
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 1);
+    database.set_format(ArchiveFormat::Indexed);
+    write_database!((&database) data = new_u8(3)).unwrap();
+    drop(database);
+    let database = LazyDB::load_db(&path).unwrap();
+    assert_eq!(database.format(), ArchiveFormat::Indexed);
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 3);
+    database.close().unwrap();
+
+    // Nothing else is left next to it
+    let mut files: Vec<_> = std::fs::read_dir(tmp.get_path()).unwrap().map(|x| x.unwrap().file_name()).collect();
+    files.sort();
+    assert_eq!(files, ["app.db", "app.db.lock"]);
+
+    // Compiled files that only differ by extension don't share anything
+    let other = tmp.get_path().join("app.ldb");
+    let database = LazyDB::load_db(&path).unwrap();
+    let other_database = LazyDB::init_db(&other).unwrap();
+    write_database!((&other_database) data = new_u8(5)).unwrap();
+    drop((database, other_database));
+    assert_eq!(LazyDB::load_db(&path).unwrap().get("::data").unwrap().collect_u8().unwrap(), 3);
+
+    // Even ones with a temporary-looking extension
+    let path = tmp.get_path().join("x.tmp");
+    let database = LazyDB::init_db(&path).unwrap();
+    write_database!((&database) data = new_u8(6)).unwrap();
+    database.close().unwrap();
+    assert_eq!(LazyDB::load_db(&path).unwrap().get("::data").unwrap().collect_u8().unwrap(), 6);
+}
+
+#[test]
+fn lazy_database_metadata() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database.ldb");
+
+    let database = LazyDB::init_db(&path).unwrap();
+    let metadata = database.metadata().unwrap();
+    assert_eq!(metadata.version, VERSION);
+    assert!(metadata.created.is_some());
+    assert!(metadata.last_compiled.is_none());
+    write_container!((database.user_metadata().unwrap()) author = new_string("Dave")).unwrap();
+    assert!(database.as_container().unwrap().keys().unwrap().is_empty()); // Kept apart from the data
+    database.close().unwrap();
+
+    // Stored in the compiled file
+    let database = LazyDB::load_db(&path).unwrap();
+    let metadata = database.metadata().unwrap();
+    assert!(metadata.last_compiled.unwrap() >= metadata.created.unwrap());
+    assert_eq!(database.user_metadata().unwrap().read_data("author").unwrap().collect_string().unwrap(), "Dave");
+}
+
+#[test]
+fn lazy_database_metadata_old_format() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let database = LazyDB::init(&path).unwrap();
+    write_database!((&database) data = new_u8(1)).unwrap();
+    drop(database);
+
+    // Readable by older versions of `lazy-db`: a 3 byte `.meta` file
+    assert_eq!(LazyData::load(path.join(".meta")).unwrap().collect_binary().unwrap().as_ref(), [VERSION.major as u8, VERSION.minor as u8, VERSION.build as u8]);
+
+    // Written by an older version of `lazy-db`, without a `.metadata` container
+    std::fs::remove_dir_all(path.join(".metadata")).unwrap();
+    let file = std::fs::File::create(path.join(".meta")).unwrap();
+    LazyData::new_binary(LazyWriter::new(file), &[1, 2, 1]).unwrap();
+
+    let database = LazyDB::load_dir(&path).unwrap();
+    let metadata = database.metadata().unwrap();
+    assert_eq!(metadata.version, version::Version::new(1, 2, 1));
+    assert_eq!(metadata.schema_version, 0);
+    assert!(metadata.created.is_none());
+    assert!(path.join(".metadata/escaped_keys").is_file()); // Written once it's keys are escaped
+    assert!(!path.join(".metadata/user").exists()); // Only created when metadata is written
+
+    write_container!((database.user_metadata().unwrap()) author = new_string("Dave")).unwrap();
+    assert!(path.join(".metadata/user").is_dir());
+    assert_eq!(database.metadata().unwrap().version, version::Version::new(1, 2, 1));
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 1);
+}
@@ -0,0 +1,141 @@
+#![cfg(feature = "encryption")]
+mod isol;
+use isol::*;
+use lazy_db::*;
+
+#[test]
+fn lazy_encryption_compile() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let og_string = String::from("Hello world!");
+
+    // Writing to the database and compiling with encryption
+    {
+        let database = LazyDB::init_db_encrypted(&path, "passphrase").unwrap();
+        write_database!((&database) /nested::data = new_string(&og_string)).unwrap();
+    }
+    let path = path.with_extension("ldb");
+
+    // Compiled file must not be a plain archive
+    assert!(LazyDB::load_db(&path).is_err());
+
+    // Read from the encrypted database
+    let database = LazyDB::load_db_encrypted(&path, "passphrase").unwrap();
+    let new_string = search_database!((database) /nested::data).unwrap().collect_string().unwrap();
+    assert_eq!(og_string, new_string);
+
+    // Snapshots would be stored unencrypted next to it
+    assert!(database.snapshot("first").is_err());
+}
+
+#[test]
+fn lazy_encryption_wrong_key() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+
+    // Writing to the database and compiling with encryption
+    {
+        let database = LazyDB::init_db_encrypted(&path, "passphrase").unwrap();
+        write_database!((&database) data = new_u8(12)).unwrap();
+    }
+    let path = path.with_extension("ldb");
+
+    // Wrong key must fail without decompiling
+    assert!(matches!(LazyDB::load_db_encrypted(&path, "wrong"), Err(LDBError::DecryptionFailed(_))));
+    assert!(!path.with_extension("ldb.modb").exists());
+}
+
+/// Recursively checks that no file or directory name within a path contains a string
+fn assert_not_within(path: &std::path::Path, needle: &[u8]) {
+    for entry in std::fs::read_dir(path).unwrap() {
+        let entry = entry.unwrap();
+        assert!(!entry.file_name().to_string_lossy().contains(std::str::from_utf8(needle).unwrap()));
+        if entry.file_type().unwrap().is_dir() {
+            assert_not_within(&entry.path(), needle);
+        } else {
+            let bytes = std::fs::read(entry.path()).unwrap();
+            assert!(!bytes.windows(needle.len()).any(|x| x == needle));
+        }
+    }
+}
+
+#[test]
+fn lazy_encryption_values() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+
+    // Writing to the database with encrypted values and names
+    {
+        let database = LazyDB::options().encrypt_values("passphrase", true).init(&path).unwrap();
+        write_database!((&database) /people/Dave::fav_colour = new_string("Blue")).unwrap();
+    }
+
+    // Neither the values nor the keys may be stored as plaintext
+    assert_not_within(&path, b"Blue");
+    assert_not_within(&path, b"Dave");
+    assert_not_within(&path, b"fav_colour");
+
+    // Read from the database
+    let database = LazyDB::options().encrypt_values("passphrase", true).load_dir(&path).unwrap();
+    let value = search_database!((database) /people/Dave::fav_colour).unwrap().collect_string().unwrap();
+    assert_eq!(value, "Blue");
+    drop(database);
+
+    // Wrong key must fail
+    assert!(matches!(LazyDB::options().encrypt_values("wrong", true).load_dir(&path), Err(LDBError::DecryptionFailed(_))));
+}
+
+#[test]
+fn lazy_encryption_values_compile() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+
+    // Writing to the database with encrypted values and compiling
+    {
+        let database = LazyDB::options().encrypt_values("passphrase", false).init_db(&path).unwrap();
+        write_database!((&database) /nested::data = new_string("Hello world!")).unwrap();
+    }
+    let path = path.with_extension("ldb");
+
+    // Read from the decompiled database
+    let database = LazyDB::options().encrypt_values("passphrase", false).load_db(&path).unwrap();
+    assert_not_within(database.path(), b"Hello world!");
+    let value = search_database!((database) /nested::data).unwrap().collect_string().unwrap();
+    assert_eq!(value, "Hello world!");
+}
+
+#[test]
+fn lazy_encryption_values_bound() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let options = LazyDB::options().encrypt_values("passphrase", false).clone();
+    let database = options.init(&path).unwrap();
+    write_database!((&database) /people/Dave::age = new_u8(21)).unwrap();
+    write_database!((&database) /people/Bob::age = new_u8(42)).unwrap();
+
+    // Moving re-encrypts for the new path
+    let people = database.as_container().unwrap().get_container("/people").unwrap();
+    people.rename("Bob", "Robert", Overwrite::Deny).unwrap();
+    assert_eq!(database.get("/people/Robert::age").unwrap().collect_u8().unwrap(), 42);
+    database.transaction(|txn| txn.set("/people/Dave::name", |file| LazyData::new_string(file, "Dave"))).unwrap();
+    assert_eq!(database.get("/people/Dave::name").unwrap().collect_string().unwrap(), "Dave");
+
+    // Snapshots share the bindings of the live database
+    database.snapshot("first").unwrap();
+    write_database!((&database) /people/Dave::age = new_u8(22)).unwrap();
+    assert_eq!(database.open_snapshot("first").unwrap().get("/people/Dave::age").unwrap().collect_u8().unwrap(), 21);
+    database.restore_snapshot("first").unwrap();
+    assert_eq!(database.get("/people/Dave::age").unwrap().collect_u8().unwrap(), 21);
+    drop(database);
+
+    // Encrypted files can't be swapped for each other
+    let (dave, robert) = (path.join("people/Dave/age"), path.join("people/Robert/age"));
+    std::fs::copy(&robert, &dave).unwrap();
+    let database = options.load_dir(&path).unwrap();
+    assert!(database.get("/people/Dave::age").is_err());
+    drop(database);
+
+    // Never read as stored without the passphrase
+    assert!(matches!(LazyDB::load_dir(&path), Err(LDBError::PassphraseRequired(_))));
+    assert!(matches!(LazyDB::open_read_only(&path), Err(LDBError::PassphraseRequired(_))));
+}
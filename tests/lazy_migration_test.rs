@@ -0,0 +1,134 @@
+mod isol;
+use isol::*;
+use lazy_db::*;
+use std::fs::{self, File};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[test]
+fn lazy_migration() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database.ldb");
+    let database = LazyDB::init_db(&path).unwrap();
+    write_database!((&database) name = new_string("Dave")).unwrap();
+    database.close().unwrap();
+
+    let runs = Arc::new(AtomicUsize::new(0));
+    let mut migrations = Migrations::new();
+    let counter = runs.clone();
+    migrations
+        .add(2, move |root| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            root.rename("name", "username", Overwrite::Deny)
+        })
+        .add(1, |root| write_container!((root) age = new_u8(21)));
+    assert_eq!(migrations.latest(), 2);
+
+    // Run in order, and the version is stored in the compiled file
+    let database = LazyDB::open_with_migrations(&path, &migrations).unwrap();
+    assert_eq!(database.schema_version().unwrap(), 2);
+    assert_eq!(database.get("::username").unwrap().collect_string().unwrap(), "Dave");
+    assert_eq!(database.get("::age").unwrap().collect_u8().unwrap(), 21);
+    assert_eq!(database.as_container().unwrap().keys().unwrap().len(), 2); // No backup left behind
+    database.close().unwrap();
+
+    // Never run twice
+    let database = LazyDB::open_with_migrations(&path, &migrations).unwrap();
+    assert_eq!(runs.load(Ordering::Relaxed), 1);
+    assert!(!database.is_dirty());
+    database.close().unwrap();
+
+    // A new step runs on its own
+    migrations.add(3, |root| root.remove("age"));
+    let database = LazyDB::open_with_migrations(&path, &migrations).unwrap();
+    assert_eq!(database.schema_version().unwrap(), 3);
+    assert_eq!(database.as_container().unwrap().keys().unwrap(), ["username"]);
+}
+
+#[test]
+fn lazy_migration_failure() {
+    let database = LazyDB::in_memory().unwrap();
+    write_database!((&database) /people::Dave = new_u8(21)).unwrap();
+
+    let mut migrations = Migrations::new();
+    migrations
+        .add(1, |root| root.remove("people"))
+        .add(2, |root| root.read_data("missing").map(|_| ()));
+
+    // Everything is restored, including the steps that succeeded
+    assert!(database.migrate(&migrations).is_err());
+    assert_eq!(database.schema_version().unwrap(), 0);
+    assert_eq!(database.get("/people::Dave").unwrap().collect_u8().unwrap(), 21);
+    assert_eq!(database.as_container().unwrap().keys().unwrap(), ["people"]);
+
+    // Also when a step panics
+    let mut migrations = Migrations::new();
+    migrations
+        .add(1, |root| root.remove("people"))
+        .add(2, |_| panic!("step failed"));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| database.migrate(&migrations)));
+    assert!(result.is_err());
+    assert_eq!(database.schema_version().unwrap(), 0);
+    assert_eq!(database.get("/people::Dave").unwrap().collect_u8().unwrap(), 21);
+    assert_eq!(database.as_container().unwrap().keys().unwrap(), ["people"]);
+}
+
+#[test]
+fn lazy_migration_incompatible_version() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let database = LazyDB::init(&path).unwrap();
+    write_database!((&database) data = new_u8(1)).unwrap();
+    drop(database);
+
+    // Written by an older, incompatible version of `lazy-db`
+    LazyData::new_binary(LazyWriter::new(File::create(path.join(".meta")).unwrap()), &[0, 9, 0]).unwrap();
+    assert!(matches!(LazyDB::load_dir(&path), Err(LDBError::IncompatibleVersion(..))));
+
+    // Upgraded by opening with migrations, even with none to run
+    let database = LazyDB::open_with_migrations(&path, &Migrations::new()).unwrap();
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 1);
+    assert_eq!(database.metadata().unwrap().version, VERSION);
+    drop(database);
+    LazyDB::load_dir(&path).unwrap();
+
+    // Written by a newer version: never opened (or downgraded)
+    LazyData::new_binary(LazyWriter::new(File::create(path.join(".meta")).unwrap()), &[VERSION.major as u8 + 1, 0, 0]).unwrap();
+    assert!(matches!(LazyDB::open_with_migrations(&path, &Migrations::new()), Err(LDBError::IncompatibleVersion(..))));
+    assert!(path.join(".meta").is_file()); // Left untouched
+}
+
+#[test]
+fn lazy_migration_recovery() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let database = LazyDB::init(&path).unwrap();
+    write_database!((&database) data = new_u8(1)).unwrap();
+    drop(database);
+
+    // Interrupted while backing up: the incomplete backup is discarded
+    fs::create_dir(path.join(".migration.tmp")).unwrap();
+    let database = LazyDB::load_dir(&path).unwrap();
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 1);
+    assert!(!path.join(".migration.tmp").exists());
+    drop(database);
+
+    // Interrupted while migrating: the backup is restored
+    let backup = tmp.get_path().join("backup");
+    let database = LazyDB::load_dir(&path).unwrap();
+    database.export_dir(&backup).unwrap();
+    let mut migrations = Migrations::new();
+    migrations.add(1, |root| {
+        write_container!((root) data = new_u8(2))?;
+        write_container!((root) new = new_u8(3))
+    });
+    database.migrate(&migrations).unwrap();
+    drop(database);
+    fs::rename(&backup, path.join(".migration")).unwrap();
+
+    let database = LazyDB::load_dir(&path).unwrap();
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 1);
+    assert_eq!(database.schema_version().unwrap(), 0);
+    assert_eq!(database.as_container().unwrap().keys().unwrap(), ["data"]);
+    assert!(!path.join(".migration").exists());
+}
@@ -0,0 +1,39 @@
+use lazy_db::*;
+
+#[test]
+fn lazy_path_parse() {
+    let path = LazyPath::parse("/people/Dave::age").unwrap();
+    assert_eq!(path.containers(), ["people", "Dave"]);
+    assert_eq!(path.data(), Some("age"));
+    assert_eq!(path, LazyPath::root().join_container("people").join_container("Dave").join_data("age"));
+
+    // Canonical display
+    for path in ["/", "/people", "/people/Dave::age", "::version", "/a%2Fb::c%3A%3Ad%25"] {
+        assert_eq!(LazyPath::parse(path).unwrap().to_string(), path);
+    }
+    assert_eq!(LazyPath::parse("people/Dave").unwrap().to_string(), "/people/Dave");
+    assert_eq!(LazyPath::parse("/a%2Fb").unwrap().containers(), ["a/b"]);
+    assert_eq!(LazyPath::parse("/a%2fb%5C").unwrap().containers(), ["a/b\\"]);
+
+    // Invalid paths
+    for path in ["/a::b::c", "/a//b", "/../etc", "/.meta", "/a::b%zz"] {
+        assert!(LazyPath::parse(path).is_err(), "{path}");
+    }
+}
+
+#[test]
+fn lazy_path_get_set_remove() {
+    let database = LazyDB::in_memory().unwrap();
+
+    // Paths from strings work the same as the macros
+    database.set("/people/Dave::age", |file| LazyData::new_u8(file, 21)).unwrap();
+    let age = search_database!((&database) /people/Dave::age).unwrap().collect_u8().unwrap();
+    assert_eq!(age, 21);
+    let path: LazyPath = String::from("/people/Dave::age").parse().unwrap();
+    assert_eq!(database.get(&path).unwrap().collect_u8().unwrap(), 21);
+
+    // Removing
+    database.remove("/people/Dave").unwrap();
+    assert!(matches!(database.get(&path), Err(LDBError::DirNotFound(_))));
+    assert!(database.remove("/").is_err());
+}
@@ -0,0 +1,114 @@
+mod isol;
+use isol::*;
+use lazy_db::*;
+use std::fs;
+
+#[test]
+fn lazy_snapshot() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let database = LazyDB::init(&path).unwrap();
+    write_database!((&database) /people::Dave = new_u8(21)).unwrap();
+    write_database!((&database) count = new_u8(1)).unwrap();
+    database.snapshot("before import").unwrap();
+    assert!(matches!(database.snapshot("before import"), Err(LDBError::AlreadyExists(_))));
+
+    // Later writes never change the snapshot
+    write_database!((&database) /people::Jack = new_u8(30)).unwrap();
+    write_database!((&database) count = new_u8(2)).unwrap();
+    let snapshot = database.open_snapshot("before import").unwrap();
+    assert_eq!(snapshot.get("::count").unwrap().collect_u8().unwrap(), 1);
+    assert_eq!(snapshot.as_container().unwrap().read_container("people").unwrap().keys().unwrap(), ["Dave"]);
+    drop(snapshot);
+
+    // Unchanged files are shared rather than copied
+    #[cfg(unix)] {
+        use std::os::unix::fs::MetadataExt;
+        let live = fs::metadata(path.join("people/Dave")).unwrap();
+        let snapshotted = fs::metadata(path.join(".snapshots/before import/people/Dave")).unwrap();
+        assert_eq!(live.ino(), snapshotted.ino());
+        assert!(fs::metadata(path.join("count")).unwrap().ino() != fs::metadata(path.join(".snapshots/before import/count")).unwrap().ino());
+    }
+
+    // Rolled back, keeping the snapshot
+    database.snapshot("after import").unwrap();
+    database.restore_snapshot("before import").unwrap();
+    assert_eq!(database.get("::count").unwrap().collect_u8().unwrap(), 1);
+    assert!(database.get("/people::Jack").is_err());
+    assert_eq!(database.list_snapshots().unwrap(), ["after import", "before import"]);
+    assert_eq!(database.as_container().unwrap().keys().unwrap().len(), 2); // Snapshots aren't data
+
+    database.remove_snapshot("after import").unwrap();
+    assert_eq!(database.list_snapshots().unwrap(), ["before import"]);
+    assert!(matches!(database.open_snapshot("after import"), Err(LDBError::DirNotFound(_))));
+}
+
+#[test]
+fn lazy_snapshot_in_memory() {
+    let database = LazyDB::in_memory().unwrap();
+    assert!(database.list_snapshots().unwrap().is_empty());
+    write_database!((&database) data = new_string("old")).unwrap();
+    database.snapshot("old").unwrap();
+    write_database!((&database) data = new_string("new")).unwrap();
+    write_database!((&database) extra = new_u8(1)).unwrap();
+
+    assert_eq!(database.open_snapshot("old").unwrap().get("::data").unwrap().collect_string().unwrap(), "old");
+    database.restore_snapshot("old").unwrap();
+    assert_eq!(database.get("::data").unwrap().collect_string().unwrap(), "old");
+    assert_eq!(database.as_container().unwrap().keys().unwrap(), ["data"]);
+}
+
+#[test]
+fn lazy_snapshot_compiled() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database.ldb");
+    let database = LazyDB::init_db(&path).unwrap();
+    write_database!((&database) data = new_u8(1)).unwrap();
+    database.snapshot("first").unwrap();
+    write_database!((&database) data = new_u8(2)).unwrap();
+    database.close().unwrap();
+
+    // Left out of the archive, but kept next to it
+    LazyDB::decompile(&path, tmp.get_path().join("database")).unwrap();
+    assert!(!tmp.get_path().join("database/.snapshots").exists());
+    assert!(tmp.get_path().join("database.ldb.snapshots/first").is_dir());
+
+    // And still there once it's loaded again
+    let database = LazyDB::load_db(&path).unwrap();
+    assert_eq!(database.list_snapshots().unwrap(), ["first"]);
+    assert_eq!(database.open_snapshot("first").unwrap().get("::data").unwrap().collect_u8().unwrap(), 1);
+    database.restore_snapshot("first").unwrap();
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 1);
+    database.remove_snapshot("first").unwrap();
+    database.close().unwrap();
+    let database = LazyDB::load_db(&path).unwrap();
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 1);
+    assert!(database.list_snapshots().unwrap().is_empty());
+}
+
+#[test]
+fn lazy_snapshot_recovery() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let database = LazyDB::init(&path).unwrap();
+    write_database!((&database) data = new_u8(1)).unwrap();
+    database.snapshot("first").unwrap();
+    write_database!((&database) data = new_u8(2)).unwrap();
+    write_database!((&database) extra = new_u8(3)).unwrap();
+    drop(database);
+
+    // Interrupted while taking a snapshot: the incomplete snapshot is discarded
+    fs::create_dir_all(path.join(".snapshots/.staging")).unwrap();
+    let database = LazyDB::load_dir(&path).unwrap();
+    assert!(!path.join(".snapshots/.staging").exists());
+    assert_eq!(database.list_snapshots().unwrap(), ["first"]);
+    drop(database);
+
+    // Interrupted while restoring (after removing some of the data): the restore is finished
+    LazyData::new_string(LazyWriter::new(fs::File::create(path.join(".snapshots/.restoring")).unwrap()), "first").unwrap();
+    fs::remove_file(path.join("data")).unwrap();
+    let database = LazyDB::load_dir(&path).unwrap();
+    assert_eq!(database.get("::data").unwrap().collect_u8().unwrap(), 1);
+    assert_eq!(database.as_container().unwrap().keys().unwrap(), ["data"]);
+    assert!(!path.join(".snapshots/.restoring").exists());
+}
@@ -0,0 +1,171 @@
+mod isol;
+use isol::*;
+use lazy_db::*;
+
+#[test]
+fn lazy_storage_in_memory() {
+    let og_string = String::from("Hello world!");
+
+    // Writing to the in-memory database
+    let database = LazyDB::in_memory().unwrap();
+    write_database!((&database) /nested::data = new_string(&og_string)).unwrap();
+
+    // Read from the in-memory database
+    let new_string = search_database!((&database) /nested::data).unwrap().collect_string().unwrap();
+
+    // Must be equal
+    assert_eq!(og_string, new_string);
+}
+
+#[test]
+fn lazy_storage_in_memory_remove() {
+    let database = LazyDB::in_memory().unwrap();
+    write_database!((&database) /nested::data = new_u8(7)).unwrap();
+
+    // Removing the nested container must remove the data within it
+    let container = database.as_container().unwrap();
+    container.remove("nested").unwrap();
+    assert!(matches!(container.read_container("nested"), Err(LDBError::DirNotFound(_))));
+}
+
+#[test]
+fn lazy_storage_in_memory_rename() {
+    let database = LazyDB::in_memory().unwrap();
+    write_database!((&database) /nested/deeper::data = new_u8(7)).unwrap();
+    write_database!((&database) /other::data = new_u8(8)).unwrap();
+    let container = database.as_container().unwrap();
+
+    // Renaming moves everything nested within the container
+    container.rename("nested", "renamed", Overwrite::Deny).unwrap();
+    assert_eq!(container.keys().unwrap(), ["other", "renamed"]);
+    assert_eq!(search_database!((&database) /renamed/deeper::data).unwrap().collect_u8().unwrap(), 7);
+    container.rename("renamed", "other", Overwrite::Replace).unwrap();
+    assert_eq!(container.keys().unwrap(), ["other"]);
+    assert!(search_database!((&database) /other::data).is_err());
+    assert_eq!(search_database!((&database) /other/deeper::data).unwrap().collect_u8().unwrap(), 7);
+}
+
+#[test]
+fn lazy_storage_in_memory_compile() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database.ldb");
+    let og_string = String::from("Hello world!");
+
+    // Compile the in-memory database onto disk
+    let database = LazyDB::in_memory().unwrap();
+    write_database!((&database) /nested::data = new_string(&og_string)).unwrap();
+    database.compile(&path).unwrap();
+
+    // Read from the compiled database
+    let database = LazyDB::load_db(path).unwrap();
+    let new_string = search_database!((database) /nested::data).unwrap().collect_string().unwrap();
+
+    // Must be equal
+    assert_eq!(og_string, new_string);
+}
+
+#[test]
+fn lazy_storage_compile_streamed() {
+    let tmp = new_env();
+    let big = "x".repeat(100_000);
+
+    for format in [ArchiveFormat::TarLz4, ArchiveFormat::Indexed] {
+        let path = tmp.get_path().join(format!("{format:?}.ldb"));
+        let database = LazyDB::in_memory().unwrap();
+        write_database!((&database) big = new_string(&big)).unwrap();
+        write_database!((&database) small = new_string("small")).unwrap();
+        database.compile_as(&path, format).unwrap();
+
+        // The size of a file is known without reading it
+        use storage::Storage;
+        let archive = storage::ArchiveStorage::open(&path, "/").unwrap();
+        let file = std::path::Path::new("/big");
+        let len = archive.file_len(file).unwrap();
+        assert!(len > 100_000);
+        assert_eq!(len, std::io::copy(&mut archive.open_read(file).unwrap(), &mut std::io::sink()).unwrap());
+        let database = LazyDB::open_archive(&path).unwrap();
+        assert_eq!(database.get("::big").unwrap().collect_string().unwrap(), big);
+        assert_eq!(database.get("::small").unwrap().collect_string().unwrap(), "small");
+    }
+}
+
+#[test]
+fn lazy_storage_in_memory_export() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+
+    // Export the in-memory database onto disk
+    let database = LazyDB::in_memory().unwrap();
+    write_database!((&database) /nested::data = new_u32(1234)).unwrap();
+    database.export_dir(&path).unwrap();
+
+    // Read from the exported database
+    let database = LazyDB::load_dir(path).unwrap();
+    let value = search_database!((database) /nested::data).unwrap().collect_u32().unwrap();
+    assert_eq!(value, 1234);
+}
+
+#[test]
+fn lazy_storage_atomic_writes() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let database = LazyDB::options().durability(storage::Durability::Fsync).init(&path).unwrap();
+    write_database!((&database) data = new_string("old")).unwrap();
+    let container = database.as_container().unwrap();
+
+    // An unfinished writer must leave the old value (and no temporary files) behind
+    let mut writer = container.data_writer("data").unwrap();
+    writer.write(&[0, 1, 2]).unwrap();
+    assert_eq!(search_database!((&database) data).unwrap().collect_string().unwrap(), "old");
+    drop(writer);
+    assert_eq!(search_database!((&database) data).unwrap().collect_string().unwrap(), "old");
+
+    // Finished writers replace it
+    write_database!((&database) data = new_string("new")).unwrap();
+    assert_eq!(search_database!((&database) data).unwrap().collect_string().unwrap(), "new");
+    let files = std::fs::read_dir(&path).unwrap().count();
+    assert_eq!(files, 4); // `.meta`, `.metadata`, `.lock` and `data`
+}
+
+#[test]
+fn lazy_storage_write_ahead_log() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let database = LazyDB::options().write_ahead_log(true).locking(false).init(&path).unwrap();
+    write_database!((&database) /nested::a = new_u8(1)).unwrap();
+    write_database!((&database) b = new_u8(2)).unwrap();
+    write_database!((&database) c = new_u8(3)).unwrap();
+    database.remove("::c").unwrap();
+    assert!(std::fs::metadata(path.join(".wal")).unwrap().len() > 0);
+
+    // Crash before checkpointing, losing some of the applied modifications and tearing the last record
+    std::mem::forget(database);
+    std::fs::remove_dir_all(path.join("nested")).unwrap();
+    std::fs::write(path.join("c"), [0]).unwrap();
+    let mut log = std::fs::read(path.join(".wal")).unwrap();
+    log.extend_from_slice(&[0, 200, 0]);
+    std::fs::write(path.join(".wal"), log).unwrap();
+
+    // The log is replayed even without the option
+    let database = LazyDB::load_dir(&path).unwrap();
+    assert_eq!(database.get("/nested::a").unwrap().collect_u8().unwrap(), 1);
+    assert_eq!(database.get("::b").unwrap().collect_u8().unwrap(), 2);
+    assert!(database.get("::c").is_err());
+    assert_eq!(std::fs::metadata(path.join(".wal")).unwrap().len(), 0);
+    drop(database);
+
+    // Names that aren't utf8 are replayed to exactly the same file
+    #[cfg(unix)] {
+        use std::os::unix::ffi::OsStrExt;
+        use storage::Storage;
+        let name = path.join(std::ffi::OsStr::from_bytes(b"\xff"));
+        let wal = storage::WalStorage::open(std::sync::Arc::new(storage::FileStorage::new(Default::default())), &path).unwrap();
+        let mut writer = wal.open_write(&name).unwrap();
+        writer.write_all(&[7]).unwrap();
+        writer.commit().unwrap();
+        std::mem::forget(wal);
+        std::fs::remove_file(&name).unwrap();
+        drop(storage::WalStorage::open(std::sync::Arc::new(storage::FileStorage::new(Default::default())), &path).unwrap());
+        assert_eq!(std::fs::read(&name).unwrap(), [7]);
+    }
+}
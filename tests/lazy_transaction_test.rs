@@ -0,0 +1,115 @@
+mod isol;
+use isol::*;
+use lazy_db::*;
+use std::fs::{self, File};
+
+#[test]
+fn lazy_transaction_commit() {
+    let database = LazyDB::in_memory().unwrap();
+    database.set("/people/Bob::age", |file| LazyData::new_u8(file, 42)).unwrap();
+
+    database.transaction(|tx| {
+        tx.set("/people/Dave::age", |file| LazyData::new_u8(file, 21))?;
+        tx.remove("/people/Bob")?;
+
+        // Reads see the staged changes, but the database doesn't yet
+        assert_eq!(tx.get("/people/Dave::age")?.collect_u8()?, 21);
+        assert!(tx.get("/people/Bob::age").is_err());
+        assert!(database.get("/people/Dave::age").is_err());
+        assert_eq!(database.get("/people/Bob::age")?.collect_u8()?, 42);
+        Ok(())
+    }).unwrap();
+
+    assert_eq!(database.get("/people/Dave::age").unwrap().collect_u8().unwrap(), 21);
+    assert_eq!(database.as_container().unwrap().get_container("/people").unwrap().keys().unwrap(), ["Dave"]);
+    assert_eq!(database.as_container().unwrap().keys().unwrap(), ["people"]); // No shadow directory left behind
+}
+
+#[test]
+fn lazy_transaction_rollback() {
+    let database = LazyDB::in_memory().unwrap();
+    database.set("::count", |file| LazyData::new_u8(file, 1)).unwrap();
+
+    // Errors roll back
+    let result: Result<(), LDBError> = database.transaction(|tx| {
+        tx.set("::count", |file| LazyData::new_u8(file, 2))?;
+        tx.get("::missing")?;
+        Ok(())
+    });
+    assert!(result.is_err());
+    assert_eq!(database.get("::count").unwrap().collect_u8().unwrap(), 1);
+
+    // Panics roll back
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        database.transaction::<()>(|tx| {
+            tx.set("::count", |file| LazyData::new_u8(file, 3))?;
+            panic!("Halfway through");
+        })
+    }));
+    assert!(result.is_err());
+    assert_eq!(database.get("::count").unwrap().collect_u8().unwrap(), 1);
+
+    // Another transaction can still be made
+    database.transaction(|tx| tx.set("::count", |file| LazyData::new_u8(file, 4))).unwrap();
+    assert_eq!(database.get("::count").unwrap().collect_u8().unwrap(), 4);
+}
+
+#[test]
+fn lazy_transaction_containers() {
+    let database = LazyDB::in_memory().unwrap();
+    database.set("/object::a", |file| LazyData::new_u8(file, 1)).unwrap();
+    database.set("/object::b", |file| LazyData::new_u8(file, 2)).unwrap();
+
+    // A failure halfway through storing an object leaves it as it was
+    let result: Result<(), LDBError> = database.transaction(|tx| {
+        let container = tx.edit_container("/object")?;
+        write_container!((container) a = new_u8(10))?;
+        Err(LDBError::FileNotFound("halfway".into()))
+    });
+    assert!(result.is_err());
+    assert_eq!(database.get("/object::a").unwrap().collect_u8().unwrap(), 1);
+
+    database.transaction(|tx| {
+        let container = tx.edit_container("/object")?;
+        write_container!((container) a = new_u8(10))?;
+        assert_eq!(tx.get("/object::a")?.collect_u8()?, 10);
+        assert_eq!(tx.get("/object::b")?.collect_u8()?, 2);
+        Ok(())
+    }).unwrap();
+    assert_eq!(database.get("/object::a").unwrap().collect_u8().unwrap(), 10);
+    assert_eq!(database.get("/object::b").unwrap().collect_u8().unwrap(), 2);
+}
+
+#[test]
+fn lazy_transaction_recovery() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let database = LazyDB::init(&path).unwrap();
+    write_database!((&database) a = new_u8(1)).unwrap();
+    write_database!((&database) b = new_u8(2)).unwrap();
+    drop(database);
+
+    // Interrupted before committing: discarded
+    fs::create_dir(path.join(".txn")).unwrap();
+    LazyData::new_u8(LazyWriter::new(File::create(path.join(".txn/0")).unwrap()), 10).unwrap();
+    let database = LazyDB::load_dir(&path).unwrap();
+    assert_eq!(database.get("::a").unwrap().collect_u8().unwrap(), 1);
+    assert!(!path.join(".txn").exists());
+    drop(database);
+
+    // Interrupted after committing (the first write was applied): completed
+    fs::create_dir(path.join(".txn")).unwrap();
+    LazyData::new_u8(LazyWriter::new(File::create(path.join(".txn/1")).unwrap()), 20).unwrap();
+    let mut manifest = Vec::new();
+    for (tag, path) in [(0u8, "::a"), (0, "/nested::b"), (1, "::b")] {
+        manifest.push(tag);
+        manifest.extend_from_slice(&(path.len() as u32).to_le_bytes());
+        manifest.extend_from_slice(path.as_bytes());
+    }
+    fs::write(path.join(".txn/commit"), manifest).unwrap();
+    let database = LazyDB::load_dir(&path).unwrap();
+    assert_eq!(database.get("::a").unwrap().collect_u8().unwrap(), 1);
+    assert_eq!(database.get("/nested::b").unwrap().collect_u8().unwrap(), 20);
+    assert!(database.get("::b").is_err());
+    assert!(!path.join(".txn").exists());
+}
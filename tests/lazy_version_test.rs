@@ -0,0 +1,62 @@
+mod isol;
+use isol::*;
+use lazy_db::*;
+use lazy_db::version::*;
+use std::fs::File;
+
+#[test]
+fn lazy_version_ordering() {
+    assert!(Version::new(1, 2, 1) < Version::new(1, 5, 4));
+    assert!(Version::new(1, 10, 0) > Version::new(1, 9, 300));
+    assert_eq!(Version::parse("1.5.4").unwrap(), Version::new(1, 5, 4));
+    assert_eq!(Version::parse("300.0.70000").unwrap().build, 70000);
+    assert_eq!(VERSION.to_string(), env!("CARGO_PKG_VERSION"));
+}
+
+#[test]
+fn lazy_version_ranges() {
+    let matches = |req: &str, version: &str| VersionReq::parse(req).unwrap().matches(&Version::parse(version).unwrap());
+
+    assert!(matches(">=1.2, <2", "1.9.9"));
+    assert!(!matches(">=1.2, <2", "2.0.0"));
+    assert!(!matches(">1.2", "1.2.9"));
+    assert!(matches("=1.2", "1.2.7"));
+    assert!(matches("~1.2.3", "1.2.9"));
+    assert!(!matches("~1.2.3", "1.3.0"));
+    assert!(matches("1.2.3", "1.9.0")); // Caret by default
+    assert!(!matches("^0.2.3", "0.3.0"));
+    assert!(!matches("^0.0.3", "0.0.4"));
+    assert!(matches("1.*", "1.7.2"));
+    assert!(matches("*", "0.0.1"));
+    assert!(VersionReq::parse(">=1.a").is_err());
+    assert_eq!(VersionReq::parse(">= 1.2 ,<2").unwrap().to_string(), ">=1.2, <2");
+}
+
+#[test]
+fn lazy_version_policy() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("database");
+    let database = LazyDB::init(&path).unwrap();
+    drop(database);
+
+    // Written by an older minor version
+    LazyData::new_binary(LazyWriter::new(File::create(path.join(".meta")).unwrap()), &[VERSION.major as u8, 0, 0]).unwrap();
+    drop(LazyDB::load_dir(&path).unwrap());
+    drop(LazyDB::options().version_policy(VersionPolicy::SameMajor).load_dir(&path).unwrap());
+    drop(LazyDB::options().version_policy(VersionPolicy::Range(VersionReq::parse("<2").unwrap())).load_dir(&path).unwrap());
+
+    // Rejected, with both versions and the policy
+    let error = LazyDB::options().version_policy(VersionPolicy::Strict).load_dir(&path).err().unwrap();
+    assert!(error.to_string().contains("strict"));
+    match error {
+        LDBError::IncompatibleVersion(found, current, policy) => {
+            assert_eq!(found, Version::new(VERSION.major, 0, 0));
+            assert_eq!(current, VERSION);
+            assert!(matches!(policy, VersionPolicy::Strict));
+        },
+        e => panic!("Expected an incompatible version, got {e}"),
+    }
+
+    let policy = VersionPolicy::custom(|current, found| found.major == current.major && found.minor > 0);
+    assert!(matches!(LazyDB::options().version_policy(policy).load_dir(&path), Err(LDBError::IncompatibleVersion(..))));
+}